@@ -1,14 +1,45 @@
 import * as readline from "readline";
 import type { FromRust } from "./protocol.js";
-import { emit } from "./protocol.js";
-import { sendMessage, setPermissionMode, interruptSession } from "./session.js";
+import {
+  emit,
+  ack,
+  reject,
+  PROTOCOL_VERSION,
+  MIN_APP_PROTOCOL_VERSION,
+  CAPABILITIES,
+} from "./protocol.js";
+import {
+  sendMessage,
+  setPermissionMode,
+  interruptSession,
+  isBusy,
+  shutdownSession,
+} from "./session.js";
 import { resolvePermission } from "./permission-handler.js";
 
+let shuttingDown = false;
+
+// Let the running query abort cleanly before exiting, whichever way the
+// app asks us to stop.
+function shutdown(): void {
+  if (shuttingDown) return;
+  shuttingDown = true;
+  shutdownSession().finally(() => process.exit(0));
+}
+
+// A signal during shutdown means the app is done waiting for it
+function onSignal(): void {
+  if (shuttingDown) process.exit(1);
+  shutdown();
+}
+
 const rl = readline.createInterface({
   input: process.stdin,
   terminal: false,
 });
 
+// Must be the first line written
+emit({ type: "hello", protocolVersion: PROTOCOL_VERSION, capabilities: CAPABILITIES });
 emit({ type: "session_ready", sessionId: `session_${Date.now()}` });
 
 rl.on("line", async (line: string) => {
@@ -25,7 +56,12 @@ rl.on("line", async (line: string) => {
 
   switch (msg.type) {
     case "send":
-      // Fire and forget — responses stream back via emit()
+      if (isBusy()) {
+        reject(msg.id, "A turn is already in progress");
+        break;
+      }
+      ack(msg.id);
+      // Responses stream back via emit()
       sendMessage(msg.text, msg.cwd).catch((err: unknown) => {
         const message = err instanceof Error ? err.message : String(err);
         emit({ type: "error", message });
@@ -33,33 +69,49 @@ rl.on("line", async (line: string) => {
       break;
 
     case "respond_permission":
-      resolvePermission(msg.requestId, msg.decision);
+      if (resolvePermission(msg.requestId, msg.decision)) {
+        ack(msg.id);
+      } else {
+        reject(msg.id, `No pending permission request with id: ${msg.requestId}`);
+      }
       break;
 
     case "set_permission_mode":
-      setPermissionMode(msg.mode);
+      if (setPermissionMode(msg.mode)) {
+        ack(msg.id);
+      } else {
+        reject(msg.id, `Unknown permission mode: ${msg.mode}`);
+      }
+      break;
+
+    case "hello":
+      if (msg.protocolVersion < MIN_APP_PROTOCOL_VERSION) {
+        reject(
+          msg.id,
+          `App protocol version ${msg.protocolVersion} is older than this sidecar supports (${MIN_APP_PROTOCOL_VERSION})`
+        );
+      } else {
+        ack(msg.id);
+      }
       break;
 
     case "interrupt":
       interruptSession();
+      ack(msg.id);
       break;
 
-    default:
-      emit({
-        type: "error",
-        message: `Unknown message type: ${(msg as { type: string }).type}`,
-      });
-  }
-});
-
-rl.on("close", () => {
-  process.exit(0);
-});
+    case "shutdown":
+      ack(msg.id);
+      shutdown();
+      break;
 
-process.on("SIGTERM", () => {
-  process.exit(0);
+    default: {
+      const unknown = msg as { id?: number; type: string };
+      reject(unknown.id, `Unknown message type: ${unknown.type}`);
+    }
+  }
 });
 
-process.on("SIGINT", () => {
-  process.exit(0);
-});
+rl.on("close", shutdown);
+process.on("SIGTERM", onSignal);
+process.on("SIGINT", onSignal);
@@ -23,15 +23,25 @@ export function createPermissionRequest(
   });
 }
 
+// Deny everything still waiting, so a shutting-down query can unwind.
+export function denyAllPermissions(): void {
+  for (const resolve of pendingRequests.values()) {
+    resolve("deny");
+  }
+  pendingRequests.clear();
+}
+
+// Returns false if no request with this id is waiting.
 export function resolvePermission(
   requestId: string,
   decision: string
-): void {
+): boolean {
   const resolver = pendingRequests.get(requestId);
   if (resolver) {
     resolver(decision);
     pendingRequests.delete(requestId);
-  } else {
-    console.error(`No pending permission request with id: ${requestId}`);
+    return true;
   }
+  console.error(`No pending permission request with id: ${requestId}`);
+  return false;
 }
@@ -1,19 +1,47 @@
-// Messages from Rust (received on stdin)
-export type FromRust =
+// Version of this protocol; must be within the range the app accepts.
+export const PROTOCOL_VERSION = 1;
+// Oldest app protocol this sidecar still understands.
+export const MIN_APP_PROTOCOL_VERSION = 1;
+// Messages added after the first protocol, which the app only sends when
+// they are listed here.
+export const CAPABILITIES = ["permissionModes", "interrupt", "shutdown"];
+
+// Messages from Rust (received on stdin). Each carries an id that is
+// answered with exactly one "ack" or "reject".
+export type FromRust = { id?: number } & (
   | { type: "send"; text: string; cwd?: string }
   | { type: "respond_permission"; requestId: string; decision: string }
   | { type: "set_permission_mode"; mode: string }
-  | { type: "interrupt" };
+  | { type: "interrupt" }
+  | { type: "hello"; protocolVersion: number }
+  | { type: "shutdown" }
+);
 
 // Messages to Rust (sent on stdout)
 export type ToRust =
+  | { type: "hello"; protocolVersion: number; capabilities: string[] }
   | { type: "sdk_message"; message: unknown }
   | { type: "permission_request"; requestId: string; toolName: string; input: unknown }
   | { type: "session_ready"; sessionId: string }
   | { type: "streaming_text"; text: string }
   | { type: "turn_complete"; messages: unknown[] }
-  | { type: "error"; message: string };
+  | { type: "error"; message: string }
+  | { type: "ack"; id: number }
+  | { type: "reject"; id: number; message: string };
 
 export function emit(msg: ToRust): void {
   process.stdout.write(JSON.stringify(msg) + "\n");
 }
+
+export function ack(id: number | undefined): void {
+  if (id !== undefined) emit({ type: "ack", id });
+}
+
+// Without an id there is nobody to answer, so report it as a plain error.
+export function reject(id: number | undefined, message: string): void {
+  if (id !== undefined) {
+    emit({ type: "reject", id, message });
+  } else {
+    emit({ type: "error", message });
+  }
+}
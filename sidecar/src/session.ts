@@ -1,12 +1,22 @@
 import { query, type SDKMessage, type PermissionResult } from "@anthropic-ai/claude-agent-sdk";
 import { emit } from "./protocol.js";
-import { createPermissionRequest } from "./permission-handler.js";
+import { createPermissionRequest, denyAllPermissions } from "./permission-handler.js";
 
 let currentAbortController: AbortController | null = null;
+let currentRun: Promise<void> | null = null;
 let permissionMode: string = "default";
 
-export function setPermissionMode(mode: string): void {
+const PERMISSION_MODES = ["default", "acceptEdits", "plan", "bypass"];
+
+export function isBusy(): boolean {
+  return currentAbortController !== null;
+}
+
+// Returns false for a mode this sidecar does not know.
+export function setPermissionMode(mode: string): boolean {
+  if (!PERMISSION_MODES.includes(mode)) return false;
   permissionMode = mode;
+  return true;
 }
 
 export function interruptSession(): void {
@@ -16,10 +26,22 @@ export function interruptSession(): void {
   }
 }
 
-export async function sendMessage(
-  text: string,
-  cwd?: string
-): Promise<void> {
+// Abort the running query, if any, and wait until it has unwound.
+export async function shutdownSession(): Promise<void> {
+  interruptSession();
+  denyAllPermissions();
+  await currentRun;
+}
+
+export function sendMessage(text: string, cwd?: string): Promise<void> {
+  const run = runQuery(text, cwd);
+  currentRun = run;
+  return run.finally(() => {
+    if (currentRun === run) currentRun = null;
+  });
+}
+
+async function runQuery(text: string, cwd?: string): Promise<void> {
   currentAbortController = new AbortController();
 
   try {
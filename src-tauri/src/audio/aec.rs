@@ -0,0 +1,445 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// Most reference audio kept while nothing is capturing, in seconds. Older
+/// samples cannot line up with the microphone any more.
+const MAX_REFERENCE_SECS: u32 = 1;
+/// Geigel double-talk threshold: adaptation pauses when the microphone is
+/// louder than this share of the recent reference peak, since that means
+/// the user is talking rather than the speakers.
+const DOUBLE_TALK_RATIO: f32 = 0.5;
+/// Reference power below which the canceller passes audio straight through.
+const SILENCE_POWER: f32 = 1e-6;
+/// Longest delay between playing a sample and hearing it that the delay
+/// estimator looks for.
+const MAX_DELAY_MS: u32 = 300;
+/// Rate the delay estimator decimates to. Speech below 2 kHz correlates
+/// well enough, and it keeps the search cheap.
+const ESTIMATE_RATE: u32 = 4000;
+/// Audio compared by each delay estimate.
+const ESTIMATE_WINDOW_MS: u32 = 500;
+/// How often the delay is estimated while audio is playing.
+const ESTIMATE_INTERVAL_MS: u32 = 250;
+/// Normalized cross-correlation an estimate needs to be trusted.
+const MIN_CORRELATION: f32 = 0.3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AecConfig {
+    /// Remove the app's own playback from the microphone signal.
+    pub enabled: bool,
+    /// Longest echo path the filter can model. Longer is more robust to
+    /// device latency but costs CPU linearly.
+    pub filter_ms: u32,
+    /// NLMS step size, between 0 and 1. Larger adapts faster but is noisier.
+    pub step_size: f32,
+    /// Time from playing a sample to hearing it in the microphone, which
+    /// the filter is shifted by. Unset estimates it from the signals.
+    pub delay_ms: Option<u32>,
+}
+
+impl Default for AecConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            filter_ms: 40,
+            step_size: 0.3,
+            delay_ms: None,
+        }
+    }
+}
+
+struct ReferenceBuffer {
+    samples: VecDeque<f32>,
+    sample_rate: u32,
+}
+
+/// What the app is playing, recorded by the output stream for the echo
+/// canceller on the capture side.
+#[derive(Clone)]
+pub struct EchoReference {
+    inner: Arc<Mutex<ReferenceBuffer>>,
+}
+
+impl EchoReference {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(ReferenceBuffer {
+                samples: VecDeque::new(),
+                sample_rate: 0,
+            })),
+        }
+    }
+
+    /// Record a block of output, silence included, at the device rate.
+    pub fn push(&self, samples: &[f32], sample_rate: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sample_rate != sample_rate {
+            inner.samples.clear();
+            inner.sample_rate = sample_rate;
+        }
+        inner.samples.extend(samples);
+        let max = (sample_rate * MAX_REFERENCE_SECS) as usize;
+        if inner.samples.len() > max {
+            let excess = inner.samples.len() - max;
+            inner.samples.drain(..excess);
+        }
+    }
+
+    /// Drop buffered reference so a new capture starts aligned.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().samples.clear();
+    }
+
+    /// Take the reference matching `n` microphone samples at `sample_rate`,
+    /// padded with silence if the output has not produced that much.
+    fn take(&self, n: usize, sample_rate: u32) -> Vec<f32> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sample_rate == 0 || inner.samples.is_empty() {
+            return vec![0.0; n];
+        }
+
+        let wanted = (n as u64 * inner.sample_rate as u64 / sample_rate as u64) as usize;
+        let available = wanted.min(inner.samples.len());
+        let mut taken: Vec<f32> = inner.samples.drain(..available).collect();
+        taken.resize(wanted, 0.0);
+        if wanted == n {
+            return taken;
+        }
+
+        // Output and input run at different rates: interpolate onto the
+        // microphone's sample grid
+        let ratio = wanted as f32 / n as f32;
+        (0..n)
+            .map(|i| {
+                let pos = i as f32 * ratio;
+                let idx = pos as usize;
+                let frac = pos - idx as f32;
+                let a = taken.get(idx).copied().unwrap_or(0.0);
+                let b = taken.get(idx + 1).copied().unwrap_or(a);
+                a + (b - a) * frac
+            })
+            .collect()
+    }
+}
+
+/// Finds the delay between playback and its echo by cross-correlating the
+/// reference with the microphone signal, both decimated to `ESTIMATE_RATE`.
+struct DelayEstimator {
+    factor: usize,
+    window: usize,
+    max_lag: usize,
+    interval: usize,
+    /// The last `window + max_lag` decimated reference samples.
+    reference: VecDeque<f32>,
+    /// The last `window` decimated microphone samples.
+    mic: VecDeque<f32>,
+    /// Running sums for the decimated sample being built.
+    sums: (f32, f32, usize),
+    since_estimate: usize,
+}
+
+impl DelayEstimator {
+    fn new(sample_rate: u32) -> Self {
+        let factor = (sample_rate / ESTIMATE_RATE).max(1) as usize;
+        let rate = sample_rate as usize / factor;
+        Self {
+            factor,
+            window: rate * ESTIMATE_WINDOW_MS as usize / 1000,
+            max_lag: rate * MAX_DELAY_MS as usize / 1000,
+            interval: rate * ESTIMATE_INTERVAL_MS as usize / 1000,
+            reference: VecDeque::new(),
+            mic: VecDeque::new(),
+            sums: (0.0, 0.0, 0),
+            since_estimate: 0,
+        }
+    }
+
+    /// Add a reference and microphone sample. Returns the echo delay in
+    /// input samples whenever a confident estimate is made.
+    fn push(&mut self, reference: f32, mic: f32) -> Option<usize> {
+        self.sums = (self.sums.0 + reference, self.sums.1 + mic, self.sums.2 + 1);
+        if self.sums.2 < self.factor {
+            return None;
+        }
+        let (reference, mic, n) = std::mem::replace(&mut self.sums, (0.0, 0.0, 0));
+        self.reference.push_back(reference / n as f32);
+        self.mic.push_back(mic / n as f32);
+        if self.reference.len() > self.window + self.max_lag {
+            self.reference.pop_front();
+        }
+        if self.mic.len() > self.window {
+            self.mic.pop_front();
+        }
+
+        self.since_estimate += 1;
+        if self.since_estimate < self.interval {
+            return None;
+        }
+        self.since_estimate = 0;
+        self.estimate().map(|lag| lag * self.factor)
+    }
+
+    /// The lag, in decimated samples, at which the reference best matches
+    /// the microphone, if it matches well enough.
+    fn estimate(&mut self) -> Option<usize> {
+        let (window, max_lag) = (self.window, self.max_lag);
+        if self.mic.len() < window || self.reference.len() < window + max_lag {
+            return None;
+        }
+        let mic = self.mic.make_contiguous();
+        let reference = self.reference.make_contiguous();
+        let mic_power: f32 = mic.iter().map(|x| x * x).sum();
+        if mic_power < SILENCE_POWER * window as f32 {
+            return None;
+        }
+        // Energy of every reference window, from prefix sums
+        let mut prefix = Vec::with_capacity(reference.len() + 1);
+        prefix.push(0.0f64);
+        for x in reference.iter() {
+            prefix.push(prefix.last().unwrap() + (*x as f64) * (*x as f64));
+        }
+
+        // The microphone at time t echoes the reference at t - lag
+        let (lag, score) = (0..=max_lag)
+            .filter_map(|lag| {
+                let start = max_lag - lag;
+                let power = (prefix[start + window] - prefix[start]) as f32;
+                if power < SILENCE_POWER * window as f32 {
+                    return None;
+                }
+                let segment = &reference[start..start + window];
+                let dot: f32 = mic.iter().zip(segment).map(|(m, x)| m * x).sum();
+                Some((lag, dot / (mic_power * power).sqrt()))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        (score >= MIN_CORRELATION).then_some(lag)
+    }
+}
+
+/// Normalized LMS adaptive filter that estimates the echo of the reference
+/// in the microphone signal and subtracts it. The reference is delayed by
+/// the bulk delay of the echo path first, so the filter only has to cover
+/// the room's reverberation rather than the device latency as well.
+pub struct EchoCanceller {
+    reference: EchoReference,
+    sample_rate: u32,
+    step_size: f32,
+    /// Reference samples not yet due at the filter, `delay` of them.
+    delayed: VecDeque<f32>,
+    delay: usize,
+    /// Unset when the delay is configured.
+    estimator: Option<DelayEstimator>,
+    weights: Vec<f32>,
+    /// Reference history stored twice over so the last `taps` samples are
+    /// always a contiguous slice ending at `pos + taps`.
+    history: Vec<f32>,
+    pos: usize,
+    /// Sum of squares of the reference samples in the window.
+    power: f32,
+    /// Decaying peak of the reference, for double-talk detection.
+    peak: f32,
+}
+
+impl EchoCanceller {
+    pub fn new(config: &AecConfig, reference: EchoReference, sample_rate: u32) -> Self {
+        let taps = ((sample_rate as u64 * config.filter_ms as u64 / 1000) as usize).max(1);
+        reference.clear();
+        let mut canceller = Self {
+            reference,
+            sample_rate,
+            step_size: config.step_size.clamp(0.0, 1.0),
+            delayed: VecDeque::new(),
+            delay: 0,
+            estimator: None,
+            weights: vec![0.0; taps],
+            history: vec![0.0; taps * 2],
+            pos: 0,
+            power: 0.0,
+            peak: 0.0,
+        };
+        match config.delay_ms {
+            Some(ms) => canceller.set_echo_delay((sample_rate as u64 * ms as u64 / 1000) as usize),
+            None => canceller.estimator = Some(DelayEstimator::new(sample_rate)),
+        }
+        canceller
+    }
+
+    /// Line the filter up with an echo arriving `echo_delay` samples after
+    /// the reference. A quarter of the filter is left before it, since the
+    /// estimate is coarse and the echo path can start early.
+    fn set_echo_delay(&mut self, echo_delay: usize) {
+        let taps = self.weights.len();
+        let delay = echo_delay.saturating_sub(taps / 4);
+        // Still within the filter's reach: keep what it has learnt
+        if delay.abs_diff(self.delay) < taps / 4 {
+            return;
+        }
+        if delay > self.delay {
+            for _ in self.delay..delay {
+                self.delayed.push_front(0.0);
+            }
+        } else {
+            self.delayed.drain(..self.delay - delay);
+        }
+        self.delay = delay;
+        self.weights.fill(0.0);
+        self.history.fill(0.0);
+        self.power = 0.0;
+        self.peak = 0.0;
+    }
+
+    pub fn process(&mut self, mic: &[f32]) -> Vec<f32> {
+        let reference = self.reference.take(mic.len(), self.sample_rate);
+        let taps = self.weights.len();
+        let decay = 1.0 - 1.0 / taps as f32;
+        // Recompute rather than trust the running sum to stay exact
+        self.power = self.history[self.pos..self.pos + taps]
+            .iter()
+            .map(|x| x * x)
+            .sum();
+
+        let mut out = Vec::with_capacity(mic.len());
+        for (&d, &played) in mic.iter().zip(&reference) {
+            let estimate = self.estimator.as_mut().and_then(|e| e.push(played, d));
+            if let Some(echo_delay) = estimate {
+                self.set_echo_delay(echo_delay);
+            }
+            self.delayed.push_back(played);
+            let x = self.delayed.pop_front().unwrap_or(0.0);
+
+            let old = self.history[self.pos];
+            self.history[self.pos] = x;
+            self.history[self.pos + taps] = x;
+            self.power = (self.power + x * x - old * old).max(0.0);
+            self.peak = (self.peak * decay).max(x.abs());
+            self.pos = (self.pos + 1) % taps;
+
+            if self.power < SILENCE_POWER {
+                out.push(d);
+                continue;
+            }
+
+            let window = &self.history[self.pos..self.pos + taps];
+            let estimate: f32 = self.weights.iter().zip(window).map(|(w, x)| w * x).sum();
+            let error = d - estimate;
+
+            if d.abs() < DOUBLE_TALK_RATIO * self.peak {
+                let gain = self.step_size * error / (self.power + SILENCE_POWER);
+                for (w, x) in self.weights.iter_mut().zip(window) {
+                    *w += gain * x;
+                }
+            }
+            out.push(error);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+    const BLOCK: usize = 480;
+
+    /// Deterministic white noise in [-0.5, 0.5).
+    fn noise(len: usize) -> Vec<f32> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect()
+    }
+
+    /// The microphone hearing `played` through an echo path that starts
+    /// `delay` samples later and rings on a little.
+    fn echo(played: &[f32], delay: usize) -> Vec<f32> {
+        (0..played.len())
+            .map(|t| {
+                let at = |d: usize| t.checked_sub(d).map_or(0.0, |i| played[i]);
+                0.4 * at(delay) + 0.15 * at(delay + 7) - 0.05 * at(delay + 40)
+            })
+            .collect()
+    }
+
+    /// Run `mic` through a canceller fed `played` block by block, as the
+    /// capture and output streams would.
+    fn cancel(canceller: &mut EchoCanceller, played: &[f32], mic: &[f32]) -> Vec<f32> {
+        let reference = canceller.reference.clone();
+        played
+            .chunks(BLOCK)
+            .zip(mic.chunks(BLOCK))
+            .flat_map(|(played, mic)| {
+                reference.push(played, RATE);
+                canceller.process(mic)
+            })
+            .collect()
+    }
+
+    fn power(samples: &[f32]) -> f32 {
+        samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32
+    }
+
+    #[test]
+    fn estimates_the_delay_and_cancels_a_late_echo() {
+        // Three times what the filter alone could reach
+        let delay = RATE as usize * 120 / 1000;
+        let played = noise(RATE as usize * 3);
+        let mic = echo(&played, delay);
+        let mut canceller = EchoCanceller::new(&AecConfig::default(), EchoReference::new(), RATE);
+
+        let out = cancel(&mut canceller, &played, &mic);
+
+        let taps = canceller.weights.len();
+        assert!(canceller.delay <= delay && delay < canceller.delay + taps / 2);
+        let tail = out.len() - RATE as usize / 2;
+        let reduction = power(&out[tail..]) / power(&mic[tail..]);
+        assert!(reduction < 0.01, "echo only reduced to {}", reduction);
+    }
+
+    #[test]
+    fn configured_delay_is_used_as_is() {
+        let config = AecConfig {
+            delay_ms: Some(80),
+            ..AecConfig::default()
+        };
+        let played = noise(RATE as usize * 2);
+        let mic = echo(&played, RATE as usize * 80 / 1000);
+        let mut canceller = EchoCanceller::new(&config, EchoReference::new(), RATE);
+        assert!(canceller.estimator.is_none());
+
+        let out = cancel(&mut canceller, &played, &mic);
+
+        let tail = out.len() - RATE as usize / 2;
+        assert!(power(&out[tail..]) < power(&mic[tail..]) * 0.01);
+    }
+
+    #[test]
+    fn near_end_speech_without_playback_passes_through() {
+        let speech = noise(RATE as usize / 2);
+        let silence = vec![0.0; speech.len()];
+        let mut canceller = EchoCanceller::new(&AecConfig::default(), EchoReference::new(), RATE);
+        assert_eq!(cancel(&mut canceller, &silence, &speech), speech);
+        assert_eq!(canceller.delay, 0);
+    }
+
+    #[test]
+    fn uncorrelated_audio_gives_no_delay_estimate() {
+        let mut estimator = DelayEstimator::new(RATE);
+        let played = noise(RATE as usize * 2);
+        let mic: Vec<f32> = played.iter().rev().copied().collect();
+        let estimates: Vec<usize> = played
+            .iter()
+            .zip(&mic)
+            .filter_map(|(&x, &d)| estimator.push(x, d))
+            .collect();
+        assert!(estimates.is_empty(), "estimated {:?}", estimates);
+    }
+}
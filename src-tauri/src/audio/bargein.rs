@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info};
+
+use crate::sidecar::protocol::ToSidecar;
+use crate::state::AppState;
+use crate::voice;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BargeInConfig {
+    /// Listen while speech is playing and stop it when the user talks.
+    pub enabled: bool,
+    /// Also interrupt the agent if it is still generating.
+    pub interrupt_agent: bool,
+    /// RMS level counted as speech. Higher than for push-to-talk, since the
+    /// microphone also hears the speakers.
+    pub threshold: f32,
+    /// Continuous speech needed before playback is stopped.
+    pub min_speech_ms: u32,
+    /// Silence that ends the recording once barge-in has triggered.
+    pub end_silence_ms: u32,
+}
+
+impl Default for BargeInConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interrupt_agent: true,
+            threshold: 0.05,
+            min_speech_ms: 250,
+            end_silence_ms: 1200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BargeInEvent {
+    /// The user started talking over playback.
+    Speech,
+    /// The user stopped talking after barging in.
+    EndOfSpeech,
+}
+
+/// Watches microphone blocks captured during playback for sustained speech,
+/// then for the silence that ends the utterance.
+pub struct BargeInDetector {
+    config: BargeInConfig,
+    sample_rate: u32,
+    speech_samples: usize,
+    silence_samples: usize,
+    triggered: bool,
+    ended: bool,
+}
+
+impl BargeInDetector {
+    pub fn new(config: BargeInConfig) -> Self {
+        Self {
+            config,
+            sample_rate: 0,
+            speech_samples: 0,
+            silence_samples: 0,
+            triggered: false,
+            ended: false,
+        }
+    }
+
+    /// Set once the capture stream reports its rate. Audio before that is
+    /// ignored.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn triggered(&self) -> bool {
+        self.triggered
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Option<BargeInEvent> {
+        if self.sample_rate == 0 {
+            return None;
+        }
+        let is_speech = super::rms(samples) >= self.config.threshold;
+        let ms = |n: usize| (n as u64 * 1000 / self.sample_rate as u64) as u32;
+
+        if !self.triggered {
+            // Require contiguous speech so clicks and echo spikes don't count
+            self.speech_samples = if is_speech {
+                self.speech_samples + samples.len()
+            } else {
+                0
+            };
+            if ms(self.speech_samples) >= self.config.min_speech_ms {
+                self.triggered = true;
+                return Some(BargeInEvent::Speech);
+            }
+            return None;
+        }
+
+        if self.ended {
+            return None;
+        }
+        self.silence_samples = if is_speech {
+            0
+        } else {
+            self.silence_samples + samples.len()
+        };
+        if ms(self.silence_samples) >= self.config.end_silence_ms {
+            self.ended = true;
+            return Some(BargeInEvent::EndOfSpeech);
+        }
+        None
+    }
+}
+
+/// Start listening for barge-in as speech playback begins.
+pub fn arm(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let config = state.settings.lock().unwrap().barge_in.clone();
+    if !config.enabled {
+        return;
+    }
+    let session_id = state.sessions.lock().unwrap().active_id().to_string();
+    let mut audio = state.audio.lock().unwrap();
+    if let Err(e) = audio.start_monitoring(app_handle.clone(), config, &session_id) {
+        error!("Failed to listen for barge-in: {}", e);
+    }
+}
+
+/// Stop listening once playback has finished, unless the user already
+/// barged in and is still talking.
+pub fn disarm(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    state.audio.lock().unwrap().stop_monitoring();
+}
+
+pub(super) fn handle_event(app_handle: AppHandle, event: BargeInEvent) {
+    // Called from the audio callback; do the work off that thread
+    std::thread::spawn(move || match event {
+        BargeInEvent::Speech => on_speech(&app_handle),
+        BargeInEvent::EndOfSpeech => on_end_of_speech(&app_handle),
+    });
+}
+
+fn on_speech(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    info!("Barge-in: stopping playback");
+    state.speaker.lock().unwrap().stop();
+    let _ = app_handle.emit("barge-in", ());
+
+    let interrupt = state.settings.lock().unwrap().barge_in.interrupt_agent;
+    let Ok(sidecar) = state.sessions.lock().unwrap().active() else {
+        return;
+    };
+    if interrupt && sidecar.is_generating() {
+        if let Err(e) = sidecar.send(&ToSidecar::Interrupt) {
+            error!("Failed to interrupt agent on barge-in: {}", e);
+        }
+    }
+}
+
+fn on_end_of_speech(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    if !state.audio.lock().unwrap().is_barge_in_recording() {
+        // Push-to-talk took over the recording
+        return;
+    }
+    if let Err(e) = voice::finish_recording(&state, app_handle) {
+        error!("Failed to transcribe barge-in recording: {}", e);
+    }
+    let _ = app_handle.emit("recording-stopped", ());
+}
@@ -2,29 +2,49 @@ use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, Stream};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
 use tracing::{error, info};
 
+use super::aec::{AecConfig, EchoCanceller, EchoReference};
+
 /// Wrapper that makes Stream usable across threads.
 /// Safety: cpal Stream on Linux (ALSA) is thread-safe in practice,
 /// and we only drop it from the same thread pattern.
-struct SendStream(Stream);
+pub(super) struct SendStream(pub(super) Stream);
 unsafe impl Send for SendStream {}
 unsafe impl Sync for SendStream {}
 
+type SampleHandler = Box<dyn Fn(&[f32]) + Send>;
+
+/// The thread running echo cancellation for the current stream, which
+/// stops on `None`.
+type AecWorker = (mpsc::Sender<Option<Vec<f32>>>, JoinHandle<()>);
+
 pub struct AudioCapture {
     stream: Option<SendStream>,
     is_recording: Arc<AtomicBool>,
+    echo_reference: EchoReference,
+    aec: AecConfig,
+    aec_worker: Option<AecWorker>,
 }
 
 impl AudioCapture {
-    pub fn new() -> Self {
+    pub fn new(echo_reference: EchoReference) -> Self {
         Self {
             stream: None,
             is_recording: Arc::new(AtomicBool::new(false)),
+            echo_reference,
+            aec: AecConfig::default(),
+            aec_worker: None,
         }
     }
 
+    /// Takes effect from the next `start`.
+    pub fn set_echo_cancellation(&mut self, config: AecConfig) {
+        self.aec = config;
+    }
+
     pub fn start<F>(&mut self, on_samples: F) -> Result<u32>
     where
         F: Fn(&[f32]) + Send + 'static,
@@ -50,6 +70,25 @@ impl AudioCapture {
             config.sample_format()
         );
 
+        // Remove the app's own playback before anyone sees the samples. The
+        // filter is too heavy for the device callback, so it gets a thread.
+        let on_samples: SampleHandler = if self.aec.enabled {
+            let mut canceller =
+                EchoCanceller::new(&self.aec, self.echo_reference.clone(), sample_rate);
+            let (sender, receiver) = mpsc::channel::<Option<Vec<f32>>>();
+            let worker = thread::spawn(move || {
+                while let Ok(Some(samples)) = receiver.recv() {
+                    on_samples(&canceller.process(&samples));
+                }
+            });
+            self.aec_worker = Some((sender.clone(), worker));
+            Box::new(move |samples: &[f32]| {
+                let _ = sender.send(Some(samples.to_vec()));
+            })
+        } else {
+            Box::new(on_samples)
+        };
+
         let is_recording = self.is_recording.clone();
         is_recording.store(true, Ordering::SeqCst);
 
@@ -120,6 +159,11 @@ impl AudioCapture {
         if let Some(stream) = self.stream.take() {
             drop(stream);
         }
+        // Deliver what is still queued before the caller reads the samples
+        if let Some((sender, worker)) = self.aec_worker.take() {
+            let _ = sender.send(None);
+            let _ = worker.join();
+        }
     }
 
     pub fn is_recording(&self) -> bool {
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use super::file::decode_file;
+use super::playback::AudioPlayer;
+use crate::state::AppState;
+
+/// Rate tones are synthesized at.
+const TONE_SAMPLE_RATE: u32 = 44100;
+/// Fade applied to both ends of each tone so it doesn't click.
+const FADE_MS: u32 = 5;
+
+/// App events that have a cue sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Earcon {
+    RecordingStarted,
+    RecordingStopped,
+    TranscriptionReady,
+    PermissionRequest,
+    TurnComplete,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CueSound {
+    /// A sequence of sine tones, one per frequency.
+    #[serde(rename_all = "camelCase")]
+    Tones {
+        frequencies: Vec<f32>,
+        note_ms: u32,
+    },
+    /// An audio file in any format `transcribe_file` accepts.
+    File {
+        path: PathBuf,
+    },
+    Silent,
+}
+
+impl CueSound {
+    fn tones(frequencies: &[f32], note_ms: u32) -> Self {
+        Self::Tones {
+            frequencies: frequencies.to_vec(),
+            note_ms,
+        }
+    }
+
+    fn render(&self) -> Result<(Vec<f32>, u32)> {
+        match self {
+            Self::Tones {
+                frequencies,
+                note_ms,
+            } => {
+                let samples = frequencies
+                    .iter()
+                    .flat_map(|&f| tone(f, *note_ms))
+                    .collect();
+                Ok((samples, TONE_SAMPLE_RATE))
+            }
+            Self::File { path } => decode_file(path, |_| {}),
+            Self::Silent => Ok((Vec::new(), TONE_SAMPLE_RATE)),
+        }
+    }
+}
+
+fn tone(frequency: f32, ms: u32) -> Vec<f32> {
+    let len = (TONE_SAMPLE_RATE * ms / 1000) as usize;
+    let fade = ((TONE_SAMPLE_RATE * FADE_MS / 1000) as usize).min(len / 2);
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / TONE_SAMPLE_RATE as f32;
+            let envelope = (i.min(len - 1 - i) as f32 / fade.max(1) as f32).min(1.0);
+            (2.0 * std::f32::consts::PI * frequency * t).sin() * envelope
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EarconConfig {
+    pub enabled: bool,
+    /// 0.0 to 1.0, applied to every cue.
+    pub volume: f32,
+    /// Overrides for the built-in sounds.
+    pub sounds: HashMap<Earcon, CueSound>,
+}
+
+impl Default for EarconConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.25,
+            sounds: HashMap::new(),
+        }
+    }
+}
+
+impl EarconConfig {
+    fn sound(&self, earcon: Earcon) -> CueSound {
+        if let Some(sound) = self.sounds.get(&earcon) {
+            return sound.clone();
+        }
+        match earcon {
+            Earcon::RecordingStarted => CueSound::tones(&[660.0, 880.0], 60),
+            Earcon::RecordingStopped => CueSound::tones(&[880.0, 660.0], 60),
+            Earcon::TranscriptionReady => CueSound::tones(&[1047.0], 50),
+            Earcon::PermissionRequest => CueSound::tones(&[784.0, 0.0, 784.0], 90),
+            Earcon::TurnComplete => CueSound::tones(&[523.0, 659.0, 784.0], 70),
+            Earcon::Error => CueSound::tones(&[220.0, 185.0], 150),
+        }
+    }
+}
+
+/// Plays short cue sounds over whatever else is on the output device.
+pub struct CuePlayer {
+    config: EarconConfig,
+    player: Arc<AudioPlayer>,
+    /// Rendered sounds, cleared whenever the config changes.
+    cache: HashMap<Earcon, (Vec<f32>, u32)>,
+}
+
+impl CuePlayer {
+    pub fn new(config: EarconConfig, player: Arc<AudioPlayer>) -> Self {
+        Self {
+            config,
+            player,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: EarconConfig) {
+        if config != self.config {
+            self.config = config;
+            self.cache.clear();
+        }
+    }
+
+    pub fn play(&mut self, earcon: Earcon) {
+        if !self.config.enabled {
+            return;
+        }
+        if !self.cache.contains_key(&earcon) {
+            match self.config.sound(earcon).render() {
+                Ok((samples, rate)) => {
+                    let volume = self.config.volume.clamp(0.0, 1.0);
+                    let samples = samples.iter().map(|s| s * volume).collect();
+                    self.cache.insert(earcon, (samples, rate));
+                }
+                Err(e) => {
+                    warn!("Failed to load {:?} cue: {}", earcon, e);
+                    return;
+                }
+            }
+        }
+
+        let (samples, rate) = &self.cache[&earcon];
+        if samples.is_empty() {
+            return;
+        }
+        if let Err(e) = self.player.play_cue(samples, *rate) {
+            warn!("Failed to play {:?} cue: {}", earcon, e);
+        }
+    }
+}
+
+/// Play a cue for an app event, if cues are enabled.
+pub fn cue(app_handle: &AppHandle, earcon: Earcon) {
+    let state = app_handle.state::<AppState>();
+    state.cues.lock().unwrap().play(earcon);
+}
@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{info, warn};
+
+use super::filter::TranscriptFilter;
+use super::resample::AudioResampler;
+use super::stt::SpeechToText;
+use super::transcribe::{TranscribeOptions, TranscriptSegment};
+
+const WHISPER_SAMPLE_RATE: usize = 16000;
+/// Chunks stay under Whisper's 30 s window with some headroom.
+const MAX_CHUNK_SAMPLES: usize = 28 * WHISPER_SAMPLE_RATE;
+/// How far back from the chunk limit to look for a quiet place to cut.
+const CUT_SEARCH_SAMPLES: usize = 4 * WHISPER_SAMPLE_RATE;
+const CUT_FRAME_SAMPLES: usize = 480;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileStage {
+    Decoding,
+    Transcribing,
+}
+
+/// Payload of the `file-transcription-progress` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProgress {
+    pub path: String,
+    pub stage: FileStage,
+    /// 0.0 to 1.0 within the current stage.
+    pub progress: f32,
+    /// Text of the chunk just transcribed, if any.
+    pub partial: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTranscript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    pub duration_ms: i64,
+}
+
+/// Decode an audio file (WAV, FLAC, OGG/Vorbis or MP3) to mono f32 samples.
+/// Returns the samples and their sample rate.
+pub fn decode_file(path: &Path, mut on_progress: impl FnMut(f32)) -> Result<(Vec<f32>, u32)> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .context("Unsupported or corrupt audio file")?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .context("No audio track found")?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .context("Unknown sample rate")?;
+    let total_frames = track.codec_params.n_frames;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported codec")?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(e).context("Failed to read audio packet"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                let channels = spec.channels.count().max(1);
+                let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                samples.extend(
+                    buffer
+                        .samples()
+                        .chunks(channels)
+                        .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+                );
+            }
+            Err(SymphoniaError::DecodeError(e)) => {
+                warn!("Skipping undecodable packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to decode audio"),
+        }
+
+        if let Some(total) = total_frames.filter(|&t| t > 0) {
+            on_progress((packet.ts() as f32 / total as f32).min(1.0));
+        }
+    }
+    on_progress(1.0);
+
+    Ok((samples, sample_rate))
+}
+
+/// Split 16 kHz audio into chunks no longer than Whisper's window, cutting at
+/// the quietest frame near each limit so words are not split in half.
+pub fn split_chunks(audio: &[f32]) -> Vec<std::ops::Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while audio.len() - start > MAX_CHUNK_SAMPLES {
+        let limit = start + MAX_CHUNK_SAMPLES;
+        let search_start = limit - CUT_SEARCH_SAMPLES;
+        let cut = (search_start..limit)
+            .step_by(CUT_FRAME_SAMPLES)
+            .min_by(|&a, &b| frame_energy(audio, a).total_cmp(&frame_energy(audio, b)))
+            .unwrap_or(limit);
+        chunks.push(start..cut);
+        start = cut;
+    }
+    if start < audio.len() {
+        chunks.push(start..audio.len());
+    }
+    chunks
+}
+
+fn frame_energy(audio: &[f32], start: usize) -> f32 {
+    let end = (start + CUT_FRAME_SAMPLES).min(audio.len());
+    audio[start..end].iter().map(|s| s * s).sum()
+}
+
+/// Decode, resample and transcribe an audio file of any length.
+pub fn transcribe_file(
+    path: &Path,
+    engine: &dyn SpeechToText,
+    options: &TranscribeOptions,
+    filter: &TranscriptFilter,
+    mut on_progress: impl FnMut(FileProgress),
+) -> Result<FileTranscript> {
+    info!("Transcribing file: {}", path.display());
+
+    let (samples, sample_rate) = decode_file(path, |progress| {
+        on_progress(FileProgress {
+            path: path.display().to_string(),
+            stage: FileStage::Decoding,
+            progress,
+            partial: None,
+        })
+    })?;
+
+    let mut resampler = AudioResampler::new(sample_rate)?;
+    let audio = resampler.process(&samples)?;
+    let duration_ms = (audio.len() * 1000 / WHISPER_SAMPLE_RATE) as i64;
+
+    let chunks = split_chunks(&audio);
+    info!(
+        "Decoded {} ({} ms) into {} chunks",
+        path.display(),
+        duration_ms,
+        chunks.len()
+    );
+
+    let mut segments = Vec::new();
+    for (i, range) in chunks.iter().enumerate() {
+        let offset_ms = (range.start * 1000 / WHISPER_SAMPLE_RATE) as i64;
+        let transcription = engine.transcribe(&audio[range.clone()], options)?;
+
+        let partial = match filter.check(&transcription) {
+            Some(reason) => {
+                info!("Dropping chunk {}: {:?}", i, reason);
+                None
+            }
+            None if transcription.text.is_empty() => None,
+            None => {
+                let mut chunk_segments = transcription.segments;
+                // Remote servers are not required to return segments
+                if chunk_segments.is_empty() {
+                    chunk_segments.push(TranscriptSegment {
+                        start_ms: 0,
+                        end_ms: (range.len() * 1000 / WHISPER_SAMPLE_RATE) as i64,
+                        text: transcription.text.clone(),
+                        avg_logprob: 0.0,
+                    });
+                }
+                segments.extend(chunk_segments.into_iter().map(|mut s| {
+                    s.start_ms += offset_ms;
+                    s.end_ms += offset_ms;
+                    s
+                }));
+                Some(transcription.text)
+            }
+        };
+
+        on_progress(FileProgress {
+            path: path.display().to_string(),
+            stage: FileStage::Transcribing,
+            progress: (i + 1) as f32 / chunks.len() as f32,
+            partial,
+        });
+    }
+
+    let text = segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(FileTranscript {
+        text,
+        segments,
+        duration_ms,
+    })
+}
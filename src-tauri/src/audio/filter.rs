@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+
+use super::transcribe::Transcription;
+
+/// Phrases Whisper is known to produce from silence or background noise,
+/// mostly learned from subtitled video in its training data.
+const DEFAULT_HALLUCINATIONS: &[&str] = &[
+    "thanks for watching",
+    "thank you for watching",
+    "thank you so much for watching",
+    "please subscribe",
+    "like and subscribe",
+    "subtitles by the amara.org community",
+    "[blank_audio]",
+    "[music]",
+    "[silence]",
+];
+
+/// Hallucinations that are also things people say, so they are only
+/// rejected when Whisper was unsure of them.
+const DEFAULT_SUSPECT_PHRASES: &[&str] = &["you", "thank you"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FilterConfig {
+    pub enabled: bool,
+    /// Reject when the audio holds less than this much speech, however long
+    /// the recording.
+    pub min_speech_ms: u64,
+    /// Reject when the mean token log-probability falls below this.
+    pub min_avg_logprob: f32,
+    /// Transcripts matching one of these (case- and punctuation-insensitive)
+    /// are rejected outright.
+    pub hallucination_phrases: Vec<String>,
+    /// Transcripts matching one of these are rejected only when their mean
+    /// token log-probability falls below `suspect_min_avg_logprob`.
+    pub suspect_phrases: Vec<String>,
+    pub suspect_min_avg_logprob: f32,
+    /// Reject when any word n-gram repeats more than this many times in a row.
+    pub max_repetitions: usize,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_speech_ms: 100,
+            min_avg_logprob: -1.0,
+            hallucination_phrases: DEFAULT_HALLUCINATIONS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            suspect_phrases: DEFAULT_SUSPECT_PHRASES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            suspect_min_avg_logprob: -0.5,
+            max_repetitions: 3,
+        }
+    }
+}
+
+/// Why a transcript was withheld.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum FilterReason {
+    #[serde(rename_all = "camelCase")]
+    NoSpeech { speech_ms: u64 },
+    #[serde(rename_all = "camelCase")]
+    LowConfidence { avg_logprob: f32 },
+    #[serde(rename_all = "camelCase")]
+    KnownHallucination { phrase: String },
+    #[serde(rename_all = "camelCase")]
+    Repetition { ngram: String, count: usize },
+}
+
+/// Payload of the `transcription-filtered` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilteredTranscript {
+    pub text: String,
+    #[serde(flatten)]
+    pub reason: FilterReason,
+}
+
+#[derive(Clone)]
+pub struct TranscriptFilter {
+    config: FilterConfig,
+}
+
+impl TranscriptFilter {
+    pub fn new(config: FilterConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn set_config(&mut self, config: FilterConfig) {
+        self.config = config;
+    }
+
+    /// Returns the reason the transcript should be dropped, or `None` if it
+    /// looks like genuine speech.
+    pub fn check(&self, transcription: &Transcription) -> Option<FilterReason> {
+        if !self.config.enabled || transcription.text.is_empty() {
+            return None;
+        }
+
+        if transcription.speech_ms < self.config.min_speech_ms {
+            return Some(FilterReason::NoSpeech {
+                speech_ms: transcription.speech_ms,
+            });
+        }
+
+        let normalized = normalize(&transcription.text);
+        if let Some(phrase) = self
+            .config
+            .hallucination_phrases
+            .iter()
+            .find(|p| normalize(p) == normalized)
+        {
+            return Some(FilterReason::KnownHallucination {
+                phrase: phrase.clone(),
+            });
+        }
+
+        let avg_logprob = transcription.avg_logprob();
+        if !transcription.segments.is_empty() && avg_logprob < self.config.min_avg_logprob {
+            return Some(FilterReason::LowConfidence { avg_logprob });
+        }
+
+        if !transcription.segments.is_empty() && avg_logprob < self.config.suspect_min_avg_logprob {
+            if let Some(phrase) = self
+                .config
+                .suspect_phrases
+                .iter()
+                .find(|p| normalize(p) == normalized)
+            {
+                return Some(FilterReason::KnownHallucination {
+                    phrase: phrase.clone(),
+                });
+            }
+        }
+
+        if self.config.max_repetitions > 0 {
+            let words: Vec<&str> = normalized.split_whitespace().collect();
+            if let Some((ngram, count)) = longest_repeat_run(&words) {
+                if count > self.config.max_repetitions {
+                    return Some(FilterReason::Repetition { ngram, count });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Lowercase and strip everything but letters, digits, brackets and spaces.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '[' || c == ']' || c == '_' {
+                Some(c.to_ascii_lowercase())
+            } else if c.is_whitespace() {
+                Some(' ')
+            } else {
+                None
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find the n-gram (up to 4 words) with the most consecutive repetitions.
+fn longest_repeat_run(words: &[&str]) -> Option<(String, usize)> {
+    let mut best: Option<(String, usize)> = None;
+    for n in 1..=4.min(words.len()) {
+        let mut start = 0;
+        while start + n <= words.len() {
+            let gram = &words[start..start + n];
+            let mut count = 1;
+            let mut next = start + n;
+            while next + n <= words.len() && &words[next..next + n] == gram {
+                count += 1;
+                next += n;
+            }
+            if count > 1 && best.as_ref().is_none_or(|(_, c)| count > *c) {
+                best = Some((gram.join(" "), count));
+            }
+            start += 1;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::transcribe::TranscriptSegment;
+
+    fn transcription(text: &str, avg_logprob: f32, speech_ms: u64) -> Transcription {
+        Transcription {
+            text: text.to_string(),
+            segments: vec![TranscriptSegment {
+                start_ms: 0,
+                end_ms: 1000,
+                text: text.to_string(),
+                avg_logprob,
+            }],
+            language: Some("en".to_string()),
+            speech_ms,
+        }
+    }
+
+    fn check(text: &str, avg_logprob: f32, speech_ms: u64) -> Option<FilterReason> {
+        TranscriptFilter::new(FilterConfig::default()).check(&transcription(
+            text,
+            avg_logprob,
+            speech_ms,
+        ))
+    }
+
+    #[test]
+    fn keeps_ordinary_speech() {
+        assert_eq!(check("Open the settings file", -0.3, 1200), None);
+    }
+
+    #[test]
+    fn keeps_short_answer_in_long_window() {
+        // 0.3 s of "yes" in a 4 s listening window
+        assert_eq!(check("Yes.", -0.2, 300), None);
+    }
+
+    #[test]
+    fn rejects_audio_without_speech() {
+        assert_eq!(
+            check("Hello there", -0.2, 0),
+            Some(FilterReason::NoSpeech { speech_ms: 0 })
+        );
+    }
+
+    #[test]
+    fn rejects_known_hallucination_regardless_of_punctuation() {
+        assert_eq!(
+            check("Thanks for watching!", -0.1, 800),
+            Some(FilterReason::KnownHallucination {
+                phrase: "thanks for watching".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn suspect_phrase_needs_low_confidence() {
+        assert_eq!(check("Thank you.", -0.2, 400), None);
+        assert_eq!(
+            check("Thank you.", -0.7, 400),
+            Some(FilterReason::KnownHallucination {
+                phrase: "thank you".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_low_confidence() {
+        assert_eq!(
+            check("something unclear", -1.5, 800),
+            Some(FilterReason::LowConfidence { avg_logprob: -1.5 })
+        );
+    }
+
+    #[test]
+    fn rejects_repetition_loops() {
+        assert_eq!(
+            check("go on go on go on go on go on", -0.2, 2000),
+            Some(FilterReason::Repetition {
+                ngram: "go on".to_string(),
+                count: 5
+            })
+        );
+        assert_eq!(check("no no", -0.2, 500), None);
+    }
+
+    #[test]
+    fn disabled_filter_keeps_everything() {
+        let filter = TranscriptFilter::new(FilterConfig {
+            enabled: false,
+            ..FilterConfig::default()
+        });
+        assert_eq!(filter.check(&transcription("you", -3.0, 0)), None);
+    }
+
+    #[test]
+    fn normalize_strips_punctuation_and_case() {
+        assert_eq!(
+            normalize("  Hello,  World! [Music] "),
+            "hello world [music]"
+        );
+    }
+
+    #[test]
+    fn measures_speech_not_share_of_window() {
+        use crate::audio::transcribe::measure_speech_ms;
+
+        // 300 ms of tone starting on a 30 ms frame boundary
+        let mut audio = vec![0.0f32; 16_000 * 4];
+        for sample in &mut audio[19_200..19_200 + 4_800] {
+            *sample = 0.2;
+        }
+        assert_eq!(measure_speech_ms(&audio), 300);
+        assert_eq!(measure_speech_ms(&vec![0.0; 16_000]), 0);
+    }
+}
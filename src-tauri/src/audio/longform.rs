@@ -0,0 +1,399 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tracing::{error, info};
+
+use super::filter::TranscriptFilter;
+use super::resample::AudioResampler;
+use super::stt::SpeechToText;
+use super::transcribe::TranscribeOptions;
+
+const SAMPLE_RATE: usize = 16000;
+
+/// Words from the end of the previous chunk searched for a repeat.
+const STITCH_TAIL_WORDS: usize = 20;
+/// Words at the start of a chunk that may be skipped before the repeat, since
+/// the first word of the overlap is often cut mid-way.
+const STITCH_MAX_SKIP: usize = 3;
+/// Shortest repeat treated as overlap rather than coincidence.
+const STITCH_MIN_MATCH: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LongFormConfig {
+    /// Length of each chunk handed to the transcriber.
+    pub chunk_secs: f32,
+    /// Audio shared between consecutive chunks so words on a boundary are
+    /// heard whole at least once.
+    pub overlap_secs: f32,
+    /// Where transcripts are written, relative to the project directory.
+    pub directory: PathBuf,
+}
+
+impl Default for LongFormConfig {
+    fn default() -> Self {
+        Self {
+            chunk_secs: 20.0,
+            overlap_secs: 2.0,
+            directory: PathBuf::from(".voxcode").join("transcripts"),
+        }
+    }
+}
+
+/// A stitched stretch of the transcript. Payload of `long-form-segment`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LongFormSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+/// A running long-form transcription: captured audio arrives over a channel
+/// at a worker that transcribes it chunk by chunk and appends to `path`.
+pub struct LongFormSession {
+    path: PathBuf,
+    worker: JoinHandle<()>,
+}
+
+impl LongFormSession {
+    /// Create the Markdown file and start the worker. `sample_rate` is the
+    /// capture rate of the samples arriving on `receiver`; the session ends
+    /// once every sender is dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        app_handle: AppHandle,
+        project_dir: &Path,
+        config: &LongFormConfig,
+        receiver: Receiver<Vec<f32>>,
+        sample_rate: u32,
+        engine: Arc<dyn SpeechToText>,
+        options: TranscribeOptions,
+        filter: TranscriptFilter,
+    ) -> Result<Self> {
+        let dir = project_dir.join(&config.directory);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let now = chrono::Local::now();
+        let path = dir.join(format!("{}.md", now.format("%Y-%m-%d_%H-%M-%S")));
+        let mut file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        writeln!(file, "# Transcript {}\n", now.format("%Y-%m-%d %H:%M"))?;
+
+        let chunk = ((config.chunk_secs * SAMPLE_RATE as f32) as usize).max(5 * SAMPLE_RATE);
+        let overlap = ((config.overlap_secs * SAMPLE_RATE as f32) as usize).min(chunk / 2);
+        let resampler = AudioResampler::new(sample_rate)?;
+        let worker = Worker {
+            app_handle,
+            file,
+            engine,
+            options,
+            filter,
+            chunk,
+            overlap,
+            last_text: String::new(),
+            chunks: 0,
+        };
+        let worker = std::thread::spawn(move || worker.run(receiver, resampler));
+
+        info!("Long-form transcript: {}", path.display());
+        Ok(Self { path, worker })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait for the worker to transcribe the remaining audio. The capture
+    /// stream feeding it must already be stopped.
+    pub fn finish(self) -> PathBuf {
+        if self.worker.join().is_err() {
+            error!("Long-form transcription worker panicked");
+        }
+        info!("Long-form transcript finished: {}", self.path.display());
+        self.path
+    }
+}
+
+struct Worker {
+    app_handle: AppHandle,
+    file: File,
+    engine: Arc<dyn SpeechToText>,
+    options: TranscribeOptions,
+    filter: TranscriptFilter,
+    /// Chunk and overlap lengths in 16 kHz samples.
+    chunk: usize,
+    overlap: usize,
+    /// Text of the previous chunk, for de-duplicating the overlap.
+    last_text: String,
+    chunks: usize,
+}
+
+impl Worker {
+    fn run(mut self, receiver: Receiver<Vec<f32>>, mut resampler: AudioResampler) {
+        let step = self.chunk - self.overlap;
+        let mut audio: Vec<f32> = Vec::new();
+        // Position of `audio[0]` in the whole recording, in samples
+        let mut offset = 0usize;
+
+        while let Ok(samples) = receiver.recv() {
+            match resampler.push(&samples) {
+                Ok(resampled) => audio.extend_from_slice(&resampled),
+                Err(e) => error!("Long-form resampling failed: {}", e),
+            }
+            while audio.len() >= self.chunk {
+                self.transcribe_chunk(&audio[..self.chunk], offset);
+                audio.drain(..step);
+                offset += step;
+            }
+        }
+
+        // Whatever is left beyond the overlap has not been heard yet
+        let seen = if self.chunks > 0 { self.overlap } else { 0 };
+        if audio.len() > seen + SAMPLE_RATE / 2 {
+            self.transcribe_chunk(&audio, offset);
+        }
+    }
+
+    fn transcribe_chunk(&mut self, audio: &[f32], offset: usize) {
+        let first = self.chunks == 0;
+        self.chunks += 1;
+
+        let transcription = match self.engine.transcribe(audio, &self.options) {
+            Ok(t) => t,
+            Err(e) => {
+                error!("Long-form chunk failed: {}", e);
+                let _ = self.app_handle.emit("long-form-error", e.to_string());
+                return;
+            }
+        };
+        if let Some(reason) = self.filter.check(&transcription) {
+            info!("Long-form chunk filtered: {:?}", reason);
+            self.last_text.clear();
+            return;
+        }
+
+        let text = stitch(&self.last_text, &transcription.text);
+        self.last_text = transcription.text;
+        if text.is_empty() {
+            return;
+        }
+
+        let lead = if first { 0 } else { self.overlap };
+        let segment = LongFormSegment {
+            start_ms: to_ms(offset + lead),
+            end_ms: to_ms(offset + audio.len()),
+            text,
+        };
+        let line = format_segment(&segment);
+        if let Err(e) = writeln!(self.file, "{}\n", line).and_then(|_| self.file.flush()) {
+            error!("Failed to write long-form transcript: {}", e);
+            let _ = self.app_handle.emit("long-form-error", e.to_string());
+        }
+        let _ = self.app_handle.emit("long-form-segment", &segment);
+    }
+}
+
+fn to_ms(samples: usize) -> i64 {
+    (samples * 1000 / SAMPLE_RATE) as i64
+}
+
+/// Drop the words at the start of `next` that repeat the end of `previous`,
+/// which happens because consecutive chunks share audio.
+fn stitch(previous: &str, next: &str) -> String {
+    let tail: Vec<String> = previous
+        .split_whitespace()
+        .rev()
+        .take(STITCH_TAIL_WORDS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map(normalize)
+        .collect();
+    let words: Vec<&str> = next.split_whitespace().collect();
+    let keys: Vec<String> = words.iter().map(|w| normalize(w)).collect();
+
+    // Longest run at the start of `next` (after skipping a few words) that
+    // equals a suffix of `previous`
+    let mut best: Option<(usize, usize)> = None;
+    for skip in 0..=STITCH_MAX_SKIP.min(keys.len()) {
+        for len in (STITCH_MIN_MATCH..=tail.len().min(keys.len() - skip)).rev() {
+            if keys[skip..skip + len] == tail[tail.len() - len..] {
+                if best.is_none_or(|(_, l)| len > l) {
+                    best = Some((skip, len));
+                }
+                break;
+            }
+        }
+    }
+
+    let start = best.map_or(0, |(skip, len)| skip + len);
+    words[start..].join(" ")
+}
+
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// One Markdown paragraph: `**[00:01:20 – 00:01:40]** text`.
+fn format_segment(segment: &LongFormSegment) -> String {
+    format!(
+        "**[{} – {}]** {}",
+        format_timestamp(segment.start_ms),
+        format_timestamp(segment.end_ms),
+        segment.text
+    )
+}
+
+fn format_timestamp(ms: i64) -> String {
+    let secs = ms / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn parse_timestamp(s: &str) -> Option<i64> {
+    let mut secs = 0i64;
+    for part in s.trim().split(':') {
+        secs = secs * 60 + part.parse::<i64>().ok()?;
+    }
+    Some(secs * 1000)
+}
+
+/// Read back the segments of a transcript written by a long-form session.
+/// Lines not in the segment format, such as the heading or notes the user
+/// added, are skipped.
+pub fn read_transcript(path: &Path) -> Result<Vec<LongFormSegment>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("**[")?;
+            let (range, text) = rest.split_once("]**")?;
+            let (start, end) = range.split_once('–')?;
+            Some(LongFormSegment {
+                start_ms: parse_timestamp(start)?,
+                end_ms: parse_timestamp(end)?,
+                text: text.trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Text of the segments overlapping `start_ms..end_ms`.
+pub fn transcript_range(segments: &[LongFormSegment], start_ms: i64, end_ms: i64) -> String {
+    segments
+        .iter()
+        .filter(|s| s.end_ms > start_ms && s.start_ms < end_ms)
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `hh:mm:ss – hh:mm:ss` label for a range, used when sending it to the agent.
+pub fn range_label(start_ms: i64, end_ms: i64) -> String {
+    format!(
+        "{} – {}",
+        format_timestamp(start_ms),
+        format_timestamp(end_ms)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stitch_drops_repeated_overlap() {
+        assert_eq!(
+            stitch("we should refactor the parser", "the parser before release"),
+            "before release"
+        );
+    }
+
+    #[test]
+    fn stitch_ignores_case_punctuation_and_a_cut_first_word() {
+        assert_eq!(
+            stitch(
+                "Then we deploy to staging.",
+                "ing deploy to staging, and run the tests"
+            ),
+            "and run the tests"
+        );
+    }
+
+    #[test]
+    fn stitch_keeps_text_without_overlap() {
+        assert_eq!(stitch("first chunk here", "second chunk"), "second chunk");
+        // A single repeated word may be coincidence
+        assert_eq!(stitch("check the logs", "logs are empty"), "logs are empty");
+        assert_eq!(stitch("", "new text"), "new text");
+        assert_eq!(stitch("old text", ""), "");
+    }
+
+    #[test]
+    fn stitch_prefers_the_longest_repeat() {
+        assert_eq!(stitch("a b c d e", "c d e f"), "f",);
+    }
+
+    #[test]
+    fn timestamps_round_trip() {
+        assert_eq!(format_timestamp(3_723_000), "01:02:03");
+        assert_eq!(parse_timestamp(" 01:02:03 "), Some(3_723_000));
+        assert_eq!(parse_timestamp("00:xx:03"), None);
+    }
+
+    #[test]
+    fn reads_back_written_segments() {
+        let segments = vec![
+            LongFormSegment {
+                start_ms: 0,
+                end_ms: 20_000,
+                text: "first part".to_string(),
+            },
+            LongFormSegment {
+                start_ms: 20_000,
+                end_ms: 40_000,
+                text: "second part".to_string(),
+            },
+        ];
+        let content = format!(
+            "# Transcript\n\n{}\n\nA note of mine\n\n{}\n",
+            format_segment(&segments[0]),
+            format_segment(&segments[1])
+        );
+        let path = std::env::temp_dir().join(format!("voxcode-{}.md", uuid::Uuid::new_v4()));
+        std::fs::write(&path, content).unwrap();
+        let read = read_transcript(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), segments);
+    }
+
+    #[test]
+    fn range_covers_overlapping_segments() {
+        let segment = |start_ms, end_ms, text: &str| LongFormSegment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+        };
+        let segments = [
+            segment(0, 20_000, "one"),
+            segment(20_000, 40_000, "two"),
+            segment(40_000, 60_000, "three"),
+        ];
+        assert_eq!(transcript_range(&segments, 25_000, 45_000), "two three");
+        assert_eq!(transcript_range(&segments, 60_000, 70_000), "");
+        assert_eq!(range_label(25_000, 45_000), "00:00:25 – 00:00:45");
+    }
+}
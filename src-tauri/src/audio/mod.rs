@@ -1,69 +1,251 @@
+pub mod aec;
+pub mod bargein;
 pub mod capture;
+pub mod earcons;
+pub mod file;
+pub mod filter;
+pub mod longform;
+pub mod playback;
+pub mod remote;
 pub mod resample;
+pub mod stt;
 pub mod transcribe;
+pub mod tts;
 pub mod vad;
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
+use serde::Serialize;
 use tauri::{AppHandle, Emitter};
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
+use crate::settings::Settings;
+
+use aec::EchoReference;
+use bargein::{BargeInConfig, BargeInDetector};
 use capture::AudioCapture;
+use earcons::Earcon;
+use filter::{FilteredTranscript, TranscriptFilter};
+use longform::{LongFormConfig, LongFormSession};
+use remote::RemoteTranscriber;
 use resample::AudioResampler;
-use transcribe::Transcriber;
+use stt::{SpeechToText, SttBackend};
+use transcribe::{DecodingConfig, TranscribeOptions, Transcriber};
+
+/// Payload of the `transcription-original` event: the source-language text
+/// behind a translated transcript.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginalTranscript {
+    pub text: String,
+    pub language: Option<String>,
+}
+
+/// Everything needed to re-run the last recording through the larger
+/// refinement model in the background.
+pub struct RefineJob {
+    pub session_id: String,
+    pub engine: Arc<Transcriber>,
+    pub audio: Vec<f32>,
+    pub options: TranscribeOptions,
+}
+
+/// A model loaded for the cascade, with the path it was loaded from.
+type CascadeModel = (PathBuf, Arc<Transcriber>);
+
+/// The models for two-pass transcription. Only loaded while the cascade is
+/// enabled.
+#[derive(Clone)]
+pub struct CascadeModels {
+    draft: CascadeModel,
+    refine: CascadeModel,
+}
 
 pub struct AudioPipeline {
     capture: AudioCapture,
+    /// Local whisper-rs engine, kept loaded even while a remote backend is
+    /// selected so switching back is instant.
     transcriber: Option<Arc<Transcriber>>,
+    remote: Option<Arc<RemoteTranscriber>>,
+    model_path: Option<PathBuf>,
+    cascade: Option<CascadeModels>,
+    /// Audio of the last recording, kept for the refinement pass.
+    last_audio: Option<Vec<f32>>,
+    /// Language and translate options, keyed by session id.
+    options: HashMap<String, TranscribeOptions>,
+    /// The session the current recording was started for, and its options.
+    recording_session: String,
+    recording_options: TranscribeOptions,
+    filter: TranscriptFilter,
     audio_buffer: Arc<Mutex<Vec<f32>>>,
+    long_form: Option<LongFormSession>,
+    /// Set while the microphone is open to detect barge-in during playback.
+    barge_in: Option<Arc<Mutex<BargeInDetector>>>,
 }
 
 impl AudioPipeline {
-    pub fn new() -> Self {
+    pub fn new(echo_reference: EchoReference) -> Self {
         Self {
-            capture: AudioCapture::new(),
+            capture: AudioCapture::new(echo_reference),
             transcriber: None,
+            remote: None,
+            model_path: None,
+            cascade: None,
+            last_audio: None,
+            options: HashMap::new(),
+            recording_session: String::new(),
+            recording_options: TranscribeOptions::default(),
+            filter: TranscriptFilter::new(Default::default()),
             audio_buffer: Arc::new(Mutex::new(Vec::new())),
+            long_form: None,
+            barge_in: None,
         }
     }
 
-    pub fn load_model(&mut self, model_path: &std::path::Path) -> Result<()> {
-        let transcriber = Transcriber::new(model_path)?;
+    pub fn load_model(&mut self, model_path: &Path, config: DecodingConfig) -> Result<()> {
+        let transcriber = Transcriber::new(model_path, config)?;
         self.transcriber = Some(Arc::new(transcriber));
+        self.model_path = Some(model_path.to_path_buf());
         Ok(())
     }
 
+    /// Apply updated settings to the filter, the speech-to-text backend and
+    /// the loaded model, if any.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.filter.set_config(settings.filter.clone());
+        self.capture
+            .set_echo_cancellation(settings.echo_cancellation.clone());
+        if let (Some(transcriber), Some(model_path)) = (&self.transcriber, &self.model_path) {
+            transcriber.set_decoding_config(settings.decoding.for_model(model_path));
+        }
+
+        match &settings.stt {
+            SttBackend::Local => self.remote = None,
+            SttBackend::Remote(config) => {
+                if !self.remote.as_ref().is_some_and(|r| r.config() == config) {
+                    match RemoteTranscriber::new(config.clone()) {
+                        Ok(remote) => self.remote = Some(Arc::new(remote)),
+                        Err(e) => {
+                            error!("Failed to set up remote transcription: {}", e);
+                            self.remote = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The cascade models in use, for `load_cascade` to reuse.
+    pub fn cascade(&self) -> Option<CascadeModels> {
+        self.cascade.clone()
+    }
+
+    pub fn set_cascade(&mut self, cascade: Option<CascadeModels>) {
+        self.cascade = cascade;
+    }
+
+    /// Take the last recording for a background refinement pass, if the
+    /// cascade is enabled.
+    pub fn take_refine_job(&mut self) -> Option<RefineJob> {
+        let audio = self.last_audio.take()?;
+        let (_, engine) = &self.cascade.as_ref()?.refine;
+        Some(RefineJob {
+            session_id: self.recording_session.clone(),
+            engine: engine.clone(),
+            audio,
+            options: self.recording_options.clone(),
+        })
+    }
+
+    /// The engine transcripts are produced with: the remote backend when one
+    /// is configured, otherwise the local model.
+    pub fn engine(&self) -> Option<Arc<dyn SpeechToText>> {
+        match (&self.remote, &self.transcriber) {
+            (Some(remote), _) => Some(remote.clone() as Arc<dyn SpeechToText>),
+            (None, Some(local)) => Some(local.clone() as Arc<dyn SpeechToText>),
+            (None, None) => None,
+        }
+    }
+
+    /// The engine for the first pass of a recording: the cascade's draft
+    /// model, unless a remote backend is configured.
+    fn draft_engine(&self) -> Option<Arc<dyn SpeechToText>> {
+        match (&self.remote, &self.cascade) {
+            (None, Some(cascade)) => Some(cascade.draft.1.clone() as Arc<dyn SpeechToText>),
+            _ => self.engine(),
+        }
+    }
+
+    pub fn transcribe_options(&self, session_id: &str) -> TranscribeOptions {
+        self.options.get(session_id).cloned().unwrap_or_default()
+    }
+
+    /// Set the language/translate options for a session's recordings.
+    pub fn set_transcribe_options(&mut self, session_id: &str, options: TranscribeOptions) {
+        info!("Transcribe options for {}: {:?}", session_id, options);
+        self.options.insert(session_id.to_string(), options);
+    }
+
+    pub fn forget_session(&mut self, session_id: &str) {
+        self.options.remove(session_id);
+    }
+
     pub fn is_model_loaded(&self) -> bool {
-        self.transcriber.is_some()
+        self.engine().is_some()
     }
 
-    pub fn start_recording(&mut self, app_handle: AppHandle) -> Result<()> {
+    /// Start recording for a session, whose transcribe options are used
+    /// when the recording stops.
+    pub fn start_recording(&mut self, app_handle: AppHandle, session_id: &str) -> Result<()> {
+        if self.long_form.is_some() {
+            anyhow::bail!("Long-form transcription is running");
+        }
+        self.set_recording_session(session_id);
+        if let Some(detector) = self.barge_in.take() {
+            if detector.lock().unwrap().triggered() {
+                info!("Continuing barge-in recording");
+                return Ok(());
+            }
+            self.capture.stop();
+        }
+
         let buffer = self.audio_buffer.clone();
         buffer.lock().unwrap().clear();
 
         let app = app_handle.clone();
         let sample_rate = self.capture.start(move |samples| {
-            let rms: f32 = if samples.is_empty() {
-                0.0
-            } else {
-                let sum: f32 = samples.iter().map(|s| s * s).sum();
-                (sum / samples.len() as f32).sqrt()
-            };
-
-            let _ = app.emit("audio-level", rms);
+            let _ = app.emit("audio-level", rms(samples));
             buffer.lock().unwrap().extend_from_slice(samples);
         })?;
 
         info!("Recording started at {}Hz", sample_rate);
         let _ = app_handle.emit("recording-started", sample_rate);
+        earcons::cue(&app_handle, Earcon::RecordingStarted);
 
         Ok(())
     }
 
+    fn set_recording_session(&mut self, session_id: &str) {
+        self.recording_session = session_id.to_string();
+        self.recording_options = self.transcribe_options(session_id);
+    }
+
+    pub fn recording_session(&self) -> &str {
+        &self.recording_session
+    }
+
     pub fn stop_recording(&mut self, app_handle: AppHandle) -> Result<String> {
+        if self.long_form.is_some() {
+            anyhow::bail!("Long-form transcription is running");
+        }
         self.capture.stop();
+        self.barge_in = None;
+        self.last_audio = None;
         info!("Recording stopped");
+        earcons::cue(&app_handle, Earcon::RecordingStopped);
 
         let samples = {
             let mut buf = self.audio_buffer.lock().unwrap();
@@ -86,21 +268,216 @@ impl AudioPipeline {
             resampled.len()
         );
 
-        let transcriber = self
-            .transcriber
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Whisper model not loaded"))?
-            .clone();
+        let engine = self
+            .draft_engine()
+            .ok_or_else(|| anyhow::anyhow!("Whisper model not loaded"))?;
+
+        let options = &self.recording_options;
+        let mut transcription = engine.transcribe(&resampled, &options.source())?;
+        info!("Transcription ({}): {:?}", engine.name(), transcription.text);
+
+        // Translate mode: keep the original for reference and decode again as
+        // English, unless the speech already was English.
+        let mut original = None;
+        if options.translate && transcription.language.as_deref() != Some("en") {
+            let translated = engine.transcribe(&resampled, options)?;
+            info!("Translation: {:?}", translated.text);
+            let source = std::mem::replace(&mut transcription, translated);
+            original = Some(OriginalTranscript {
+                text: source.text,
+                language: source.language,
+            });
+        }
+
+        if let Some(reason) = self.filter.check(&transcription) {
+            info!("Transcription filtered: {:?}", reason);
+            let _ = app_handle.emit(
+                "transcription-filtered",
+                FilteredTranscript {
+                    text: transcription.text,
+                    reason,
+                },
+            );
+            return Ok(String::new());
+        }
 
-        let text = transcriber.transcribe(&resampled)?;
-        info!("Transcription: {:?}", text);
+        if let Some(original) = original {
+            let _ = app_handle.emit("transcription-original", original);
+        }
 
-        let _ = app_handle.emit("transcription", &text);
+        if self.cascade.is_some() {
+            self.last_audio = Some(resampled);
+        }
 
-        Ok(text)
+        Ok(transcription.text)
     }
 
     pub fn is_recording(&self) -> bool {
         self.capture.is_recording()
     }
+
+    /// Open the microphone while speech is playing. Audio is buffered as for
+    /// a normal recording; if the user talks over playback it becomes one
+    /// for `session_id`.
+    pub fn start_monitoring(
+        &mut self,
+        app_handle: AppHandle,
+        config: BargeInConfig,
+        session_id: &str,
+    ) -> Result<()> {
+        if self.capture.is_recording() {
+            return Ok(());
+        }
+        self.set_recording_session(session_id);
+
+        let buffer = self.audio_buffer.clone();
+        buffer.lock().unwrap().clear();
+
+        let detector = Arc::new(Mutex::new(BargeInDetector::new(config)));
+        let shared = detector.clone();
+        let app = app_handle.clone();
+        let sample_rate = self.capture.start(move |samples| {
+            buffer.lock().unwrap().extend_from_slice(samples);
+            let event = shared.lock().unwrap().process(samples);
+            if let Some(event) = event {
+                bargein::handle_event(app.clone(), event);
+            }
+            let _ = app.emit("audio-level", rms(samples));
+        })?;
+
+        detector.lock().unwrap().set_sample_rate(sample_rate);
+        self.barge_in = Some(detector);
+        info!("Listening for barge-in at {}Hz", sample_rate);
+        Ok(())
+    }
+
+    /// Close the microphone opened by `start_monitoring`, unless the user
+    /// barged in, in which case the recording carries on.
+    pub fn stop_monitoring(&mut self) {
+        let Some(detector) = &self.barge_in else {
+            return;
+        };
+        if detector.lock().unwrap().triggered() {
+            return;
+        }
+        self.barge_in = None;
+        self.capture.stop();
+        self.audio_buffer.lock().unwrap().clear();
+        info!("Stopped listening for barge-in");
+    }
+
+    /// Whether the current recording was started by barge-in.
+    pub fn is_barge_in_recording(&self) -> bool {
+        self.barge_in
+            .as_ref()
+            .is_some_and(|d| d.lock().unwrap().triggered())
+    }
+
+    /// Start continuous transcription into a Markdown file under
+    /// `project_dir`. Returns the transcript path.
+    pub fn start_long_form(
+        &mut self,
+        app_handle: AppHandle,
+        project_dir: &Path,
+        config: &LongFormConfig,
+        session_id: &str,
+    ) -> Result<PathBuf> {
+        if self.capture.is_recording() {
+            anyhow::bail!("Already recording");
+        }
+        let engine = self
+            .engine()
+            .ok_or_else(|| anyhow::anyhow!("Whisper model not loaded"))?;
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let app = app_handle.clone();
+        let sample_rate = self.capture.start(move |samples| {
+            let _ = app.emit("audio-level", rms(samples));
+            let _ = sender.send(samples.to_vec());
+        })?;
+
+        let session = LongFormSession::start(
+            app_handle.clone(),
+            project_dir,
+            config,
+            receiver,
+            sample_rate,
+            engine,
+            self.transcribe_options(session_id),
+            self.filter.clone(),
+        );
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => {
+                self.capture.stop();
+                return Err(e);
+            }
+        };
+
+        info!("Long-form transcription started at {}Hz", sample_rate);
+        let _ = app_handle.emit("recording-started", sample_rate);
+        earcons::cue(&app_handle, Earcon::RecordingStarted);
+        let path = session.path().to_path_buf();
+        self.long_form = Some(session);
+        Ok(path)
+    }
+
+    /// Stop capturing for the running long-form session. The caller finishes
+    /// the returned session off the lock, since the last chunk still has to
+    /// be transcribed.
+    pub fn stop_long_form(&mut self) -> Option<LongFormSession> {
+        let session = self.long_form.take()?;
+        self.capture.stop();
+        info!("Long-form capture stopped");
+        Some(session)
+    }
+
+    pub fn long_form_path(&self) -> Option<&Path> {
+        self.long_form.as_ref().map(|s| s.path())
+    }
+}
+
+/// Load the cascade models `settings` asks for, reusing those in `current`
+/// that have not changed. Loading a model takes seconds, so this is called
+/// without holding the pipeline and the result installed with `set_cascade`.
+pub fn load_cascade(current: Option<CascadeModels>, settings: &Settings) -> Option<CascadeModels> {
+    let cascade = &settings.cascade;
+    if !cascade.enabled {
+        return None;
+    }
+    let load = |current: Option<CascadeModel>, path: &Path| -> Option<CascadeModel> {
+        let config = settings.decoding.for_model(path);
+        if let Some((loaded, model)) = current.filter(|(loaded, _)| loaded == path) {
+            model.set_config(config);
+            return Some((loaded, model));
+        }
+        if !path.exists() {
+            warn!(
+                "Cascade model not found at {}; two-pass transcription disabled",
+                path.display()
+            );
+            return None;
+        }
+        match Transcriber::new(path, config) {
+            Ok(model) => Some((path.to_path_buf(), Arc::new(model))),
+            Err(e) => {
+                error!("Failed to load cascade model {}: {}", path.display(), e);
+                None
+            }
+        }
+    };
+    let (draft, refine) = current.map(|c| (c.draft, c.refine)).unzip();
+    Some(CascadeModels {
+        draft: load(draft, &cascade.draft_model)?,
+        refine: load(refine, &cascade.refine_model)?,
+    })
+}
+
+/// RMS level of a block of samples, for the `audio-level` meter.
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = samples.iter().map(|s| s * s).sum();
+    (sum / samples.len() as f32).sqrt()
 }
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::SampleFormat;
+use tracing::{error, info};
+
+use super::aec::EchoReference;
+use super::capture::SendStream;
+
+/// Plays mono sample buffers on the default output device. The stream is
+/// opened on first use and kept running, outputting silence while the queue
+/// is empty, so there is no start-up latency per utterance.
+pub struct AudioPlayer {
+    stream: Mutex<Option<(SendStream, u32)>>,
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    /// Cue sounds, mixed over `queue` rather than waiting behind it.
+    cues: Arc<Mutex<VecDeque<f32>>>,
+    /// Everything written to the device, for echo cancellation.
+    reference: EchoReference,
+}
+
+impl AudioPlayer {
+    pub fn new(reference: EchoReference) -> Self {
+        Self {
+            stream: Mutex::new(None),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            cues: Arc::new(Mutex::new(VecDeque::new())),
+            reference,
+        }
+    }
+
+    /// Queue `samples` recorded at `sample_rate` after anything already
+    /// playing.
+    pub fn play(&self, samples: &[f32], sample_rate: u32) -> Result<()> {
+        let device_rate = self.ensure_stream()?;
+        let samples = resample_linear(samples, sample_rate, device_rate);
+        self.queue.lock().unwrap().extend(samples);
+        Ok(())
+    }
+
+    /// Play a short sound immediately, on top of anything else playing.
+    pub fn play_cue(&self, samples: &[f32], sample_rate: u32) -> Result<()> {
+        let device_rate = self.ensure_stream()?;
+        let samples = resample_linear(samples, sample_rate, device_rate);
+        let mut cues = self.cues.lock().unwrap();
+        for (i, sample) in samples.into_iter().enumerate() {
+            match cues.get_mut(i) {
+                Some(queued) => *queued += sample,
+                None => cues.push_back(sample),
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop all queued speech, silencing it immediately. Cues play out.
+    pub fn stop(&self) {
+        self.queue.lock().unwrap().clear();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.queue.lock().unwrap().is_empty()
+    }
+
+    /// Open the output stream if needed and return its sample rate.
+    fn ensure_stream(&self) -> Result<u32> {
+        let mut guard = self.stream.lock().unwrap();
+        if let Some((_, rate)) = guard.as_ref() {
+            return Ok(*rate);
+        }
+
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .context("No default output device found")?;
+        info!("Using output device: {}", device.name().unwrap_or_default());
+
+        let config = device
+            .default_output_config()
+            .context("Failed to get default output config")?;
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels() as usize;
+
+        let err_fn = |err| error!("Audio output stream error: {}", err);
+        let queue = self.queue.clone();
+        let cues = self.cues.clone();
+        let reference = self.reference.clone();
+        let next_block = move |frames: usize| -> Vec<f32> {
+            let mut queue = queue.lock().unwrap();
+            let mut cues = cues.lock().unwrap();
+            let block: Vec<f32> = (0..frames)
+                .map(|_| {
+                    let sample = queue.pop_front().unwrap_or(0.0) + cues.pop_front().unwrap_or(0.0);
+                    sample.clamp(-1.0, 1.0)
+                })
+                .collect();
+            drop(queue);
+            drop(cues);
+            reference.push(&block, sample_rate);
+            block
+        };
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => device.build_output_stream(
+                &config.into(),
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let block = next_block(data.len() / channels);
+                    for (frame, sample) in data.chunks_mut(channels).zip(block) {
+                        frame.fill(sample);
+                    }
+                },
+                err_fn,
+                None,
+            )?,
+            SampleFormat::I16 => device.build_output_stream(
+                &config.into(),
+                move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                    let block = next_block(data.len() / channels);
+                    for (frame, sample) in data.chunks_mut(channels).zip(block) {
+                        frame.fill((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+                    }
+                },
+                err_fn,
+                None,
+            )?,
+            sample_format => {
+                anyhow::bail!("Unsupported output sample format: {:?}", sample_format);
+            }
+        };
+
+        stream.play()?;
+        info!("Audio output: {}Hz, {} channels", sample_rate, channels);
+        *guard = Some((SendStream(stream), sample_rate));
+        Ok(sample_rate)
+    }
+}
+
+/// Linear-interpolation resampler. Good enough for speech and short cues,
+/// which do not justify the FFT resampler's chunking.
+fn resample_linear(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let len = (samples.len() as f64 / ratio) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx.min(samples.len() - 1)];
+            let b = samples[(idx + 1).min(samples.len() - 1)];
+            a + (b - a) * frac
+        })
+        .collect()
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::blocking::{multipart, Client};
+use serde::Deserialize;
+use tracing::info;
+
+use super::stt::{RemoteConfig, SpeechToText};
+use super::transcribe::{measure_speech_ms, TranscribeOptions, TranscriptSegment, Transcription};
+
+const TRANSCRIPTIONS_PATH: &str = "/v1/audio/transcriptions";
+const TRANSLATIONS_PATH: &str = "/v1/audio/translations";
+const SAMPLE_RATE: u32 = 16000;
+
+/// Client for OpenAI-compatible transcription servers such as the
+/// whisper.cpp server or faster-whisper-server.
+pub struct RemoteTranscriber {
+    client: Client,
+    /// Server URL without the `/v1/audio/...` path.
+    base_url: String,
+    config: RemoteConfig,
+}
+
+#[derive(Deserialize)]
+struct VerboseResponse {
+    text: String,
+    #[serde(default)]
+    segments: Vec<VerboseSegment>,
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VerboseSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    avg_logprob: f32,
+}
+
+impl RemoteTranscriber {
+    pub fn new(config: RemoteConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        let url = config.url.trim_end_matches('/');
+        let base_url = url
+            .strip_suffix(TRANSCRIPTIONS_PATH)
+            .or_else(|| url.strip_suffix(TRANSLATIONS_PATH))
+            .unwrap_or(url)
+            .to_string();
+        info!("Remote transcription server: {}", base_url);
+
+        Ok(Self {
+            client,
+            base_url,
+            config,
+        })
+    }
+
+    fn endpoint(&self, translate: bool) -> String {
+        let path = if translate {
+            TRANSLATIONS_PATH
+        } else {
+            TRANSCRIPTIONS_PATH
+        };
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn config(&self) -> &RemoteConfig {
+        &self.config
+    }
+}
+
+impl SpeechToText for RemoteTranscriber {
+    fn transcribe(&self, audio: &[f32], options: &TranscribeOptions) -> Result<Transcription> {
+        let endpoint = self.endpoint(options.translate);
+        let file = multipart::Part::bytes(encode_wav(audio, SAMPLE_RATE))
+            .file_name("audio.wav")
+            .mime_str("audio/wav")?;
+        let mut form = multipart::Form::new()
+            .part("file", file)
+            .text("model", self.config.model.clone())
+            .text("response_format", "verbose_json")
+            .text("temperature", "0");
+        // The translations endpoint always outputs English and takes no language
+        if let (false, Some(language)) = (options.translate, &options.language) {
+            form = form.text("language", language.clone());
+        }
+
+        let mut request = self.client.post(&endpoint).multipart(form);
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .with_context(|| format!("Request to {} failed", endpoint))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Transcription server returned {}: {}", status, body.trim());
+        }
+        let body: VerboseResponse = response
+            .json()
+            .context("Invalid transcription server response")?;
+
+        let segments = body
+            .segments
+            .into_iter()
+            .map(|s| TranscriptSegment {
+                start_ms: (s.start * 1000.0) as i64,
+                end_ms: (s.end * 1000.0) as i64,
+                text: s.text.trim().to_string(),
+                avg_logprob: s.avg_logprob,
+            })
+            .collect();
+
+        Ok(Transcription {
+            text: body.text.trim().to_string(),
+            segments,
+            language: body.language,
+            speech_ms: measure_speech_ms(audio),
+        })
+    }
+
+    fn name(&self) -> String {
+        self.base_url.clone()
+    }
+}
+
+/// Encode mono f32 samples as a 16-bit PCM WAV file.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for &s in samples {
+        let v = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&v.to_le_bytes());
+    }
+    wav
+}
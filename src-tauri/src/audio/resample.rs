@@ -7,6 +7,8 @@ const WHISPER_SAMPLE_RATE: usize = 16000;
 pub struct AudioResampler {
     resampler: Option<FftFixedIn<f32>>,
     source_rate: usize,
+    /// Input left over from the last `push` that did not fill a whole chunk.
+    pending: Vec<f32>,
 }
 
 impl AudioResampler {
@@ -18,6 +20,7 @@ impl AudioResampler {
             return Ok(Self {
                 resampler: None,
                 source_rate,
+                pending: Vec::new(),
             });
         }
 
@@ -38,6 +41,7 @@ impl AudioResampler {
         Ok(Self {
             resampler: Some(resampler),
             source_rate,
+            pending: Vec::new(),
         })
     }
 
@@ -65,6 +69,16 @@ impl AudioResampler {
                     offset += frames_needed;
                 }
 
+                // Zero-pad the tail so the last partial chunk is not dropped
+                if offset < input.len() {
+                    let mut padded = input[offset..].to_vec();
+                    padded.resize(frames_needed, 0.0);
+                    let output = resampler.process(&[padded], None)?;
+                    if let Some(out) = output.into_iter().next() {
+                        all_output.extend_from_slice(&out);
+                    }
+                }
+
                 Ok(all_output)
             }
         } else {
@@ -72,6 +86,29 @@ impl AudioResampler {
         }
     }
 
+    /// Streaming variant of `process`: resamples whole chunks only and keeps
+    /// the remainder for the next call, so no padding ends up mid-stream.
+    pub fn push(&mut self, input: &[f32]) -> Result<Vec<f32>> {
+        let Some(ref mut resampler) = self.resampler else {
+            return Ok(input.to_vec());
+        };
+
+        self.pending.extend_from_slice(input);
+        let frames_needed = resampler.input_frames_next();
+        let mut all_output = Vec::new();
+        let mut offset = 0;
+        while offset + frames_needed <= self.pending.len() {
+            let chunk = &self.pending[offset..offset + frames_needed];
+            let output = resampler.process(&[chunk], None)?;
+            if let Some(out) = output.into_iter().next() {
+                all_output.extend_from_slice(&out);
+            }
+            offset += frames_needed;
+        }
+        self.pending.drain(..offset);
+        Ok(all_output)
+    }
+
     pub fn target_rate(&self) -> u32 {
         WHISPER_SAMPLE_RATE as u32
     }
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::transcribe::{DecodingConfig, TranscribeOptions, Transcription};
+
+/// A speech-to-text engine that turns 16 kHz mono samples into text.
+pub trait SpeechToText: Send + Sync {
+    fn transcribe(&self, audio: &[f32], options: &TranscribeOptions) -> Result<Transcription>;
+
+    /// Short name for logs, e.g. "whisper-rs" or the server URL.
+    fn name(&self) -> String;
+
+    /// Apply new decoding settings. Engines without local decoding ignore it.
+    fn set_decoding_config(&self, _config: DecodingConfig) {}
+}
+
+/// Which engine `AudioPipeline` transcribes with.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "camelCase")]
+pub enum SttBackend {
+    /// In-process whisper-rs with the loaded ggml model.
+    #[default]
+    Local,
+    /// An OpenAI-compatible `/v1/audio/transcriptions` server.
+    Remote(RemoteConfig),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RemoteConfig {
+    /// Base URL, e.g. `http://build-box:8080`. `/v1/audio/transcriptions` is
+    /// appended unless the URL already ends with it.
+    pub url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub timeout_secs: u64,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8080".to_string(),
+            api_key: None,
+            model: "whisper-1".to_string(),
+            timeout_secs: 60,
+        }
+    }
+}
@@ -1,14 +1,170 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use tracing::info;
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
+use super::stt::SpeechToText;
+use super::vad::VoiceActivityDetector;
+
+/// How Whisper picks tokens while decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "camelCase")]
+pub enum SamplingMode {
+    #[serde(rename_all = "camelCase")]
+    Greedy { best_of: i32 },
+    #[serde(rename_all = "camelCase")]
+    BeamSearch { beam_size: i32 },
+}
+
+impl Default for SamplingMode {
+    fn default() -> Self {
+        Self::Greedy { best_of: 1 }
+    }
+}
+
+/// Decoding knobs passed to `whisper_full`. Persisted in settings, with
+/// optional per-model overrides.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DecodingConfig {
+    pub sampling: SamplingMode,
+    /// Initial sampling temperature.
+    pub temperature: f32,
+    /// Step used to raise the temperature when a decode fails the entropy or
+    /// log-probability thresholds. 0 disables fallback.
+    pub temperature_inc: f32,
+    pub entropy_threshold: f32,
+    pub logprob_threshold: f32,
+    pub no_speech_threshold: f32,
+    /// Decoder threads. `None` uses all available cores.
+    pub n_threads: Option<usize>,
+    /// Maximum tokens per segment. 0 means no limit.
+    pub max_tokens: i32,
+}
+
+impl Default for DecodingConfig {
+    fn default() -> Self {
+        Self {
+            sampling: SamplingMode::default(),
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+            no_speech_threshold: 0.6,
+            n_threads: None,
+            max_tokens: 0,
+        }
+    }
+}
+
+impl DecodingConfig {
+    pub fn threads(&self) -> usize {
+        self.n_threads.filter(|&n| n > 0).unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+        })
+    }
+
+    fn sampling_strategy(&self) -> SamplingStrategy {
+        match self.sampling {
+            SamplingMode::Greedy { best_of } => SamplingStrategy::Greedy {
+                best_of: best_of.max(1),
+            },
+            SamplingMode::BeamSearch { beam_size } => SamplingStrategy::BeamSearch {
+                beam_size: beam_size.max(1),
+                patience: -1.0,
+            },
+        }
+    }
+}
+
+/// Per-session options for a transcription run. Unlike `DecodingConfig`
+/// these are not persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TranscribeOptions {
+    /// Spoken language code, or `None` to auto-detect.
+    pub language: Option<String>,
+    /// Use Whisper's translate task to emit English whatever the spoken
+    /// language. Requires a multilingual (non-`.en`) model.
+    pub translate: bool,
+}
+
+impl Default for TranscribeOptions {
+    fn default() -> Self {
+        Self {
+            language: Some("en".to_string()),
+            translate: false,
+        }
+    }
+}
+
+impl TranscribeOptions {
+    /// Options for a pass in the spoken language. In translate mode the
+    /// language is detected, since a pinned "en" would make every recording
+    /// look English and skip the translation.
+    pub fn source(&self) -> Self {
+        Self {
+            language: if self.translate {
+                None
+            } else {
+                self.language.clone()
+            },
+            translate: false,
+        }
+    }
+}
+
+/// One decoded Whisper segment.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    /// Mean log-probability of the segment's text tokens.
+    pub avg_logprob: f32,
+}
+
+/// Structured result of a transcription run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcription {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    /// Language code the audio was decoded as, if known.
+    pub language: Option<String>,
+    /// How much of the input is loud enough to be speech. whisper-rs does
+    /// not expose the decoder's own no-speech probability per state, so this
+    /// is measured from frame energy.
+    pub speech_ms: u64,
+}
+
+impl Transcription {
+    /// Mean of the per-segment log-probabilities.
+    pub fn avg_logprob(&self) -> f32 {
+        if self.segments.is_empty() {
+            return 0.0;
+        }
+        self.segments.iter().map(|s| s.avg_logprob).sum::<f32>() / self.segments.len() as f32
+    }
+}
+
+/// Energy threshold and frame size (30 ms at 16 kHz) for measuring speech.
+const SPEECH_RMS_THRESHOLD: f32 = 0.01;
+const SPEECH_FRAME_SAMPLES: usize = 480;
+const SPEECH_FRAME_MS: u64 = 30;
+
 pub struct Transcriber {
     ctx: WhisperContext,
+    config: RwLock<DecodingConfig>,
 }
 
 impl Transcriber {
-    pub fn new(model_path: &Path) -> Result<Self> {
+    pub fn new(model_path: &Path, config: DecodingConfig) -> Result<Self> {
         info!("Loading Whisper model from: {}", model_path.display());
 
         let ctx = WhisperContext::new_with_params(
@@ -18,13 +174,37 @@ impl Transcriber {
         .map_err(|e| anyhow::anyhow!("Failed to load Whisper model: {:?}", e))?;
 
         info!("Whisper model loaded successfully");
-        Ok(Self { ctx })
+        Ok(Self {
+            ctx,
+            config: RwLock::new(config),
+        })
     }
 
-    pub fn transcribe(&self, audio: &[f32]) -> Result<String> {
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    pub fn config(&self) -> DecodingConfig {
+        self.config.read().unwrap().clone()
+    }
 
-        params.set_language(Some("en"));
+    pub fn set_config(&self, config: DecodingConfig) {
+        info!("Whisper decoding config updated: {:?}", config);
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn transcribe(&self, audio: &[f32], options: &TranscribeOptions) -> Result<Transcription> {
+        if options.translate && !self.ctx.is_multilingual() {
+            anyhow::bail!("Translation needs a multilingual Whisper model, not an English-only one");
+        }
+
+        let config = self.config();
+        let mut params = FullParams::new(config.sampling_strategy());
+
+        // Translation detects the source language, see `TranscribeOptions::source`
+        let language = if options.translate {
+            None
+        } else {
+            options.language.as_deref()
+        };
+        params.set_language(language);
+        params.set_translate(options.translate);
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
@@ -32,8 +212,13 @@ impl Transcriber {
         params.set_suppress_blank(true);
         params.set_single_segment(true);
         params.set_no_context(true);
-        // Speed optimization for real-time
-        params.set_n_threads(4);
+        params.set_n_threads(config.threads() as i32);
+        params.set_temperature(config.temperature);
+        params.set_temperature_inc(config.temperature_inc);
+        params.set_entropy_thold(config.entropy_threshold);
+        params.set_logprob_thold(config.logprob_threshold);
+        params.set_no_speech_thold(config.no_speech_threshold);
+        params.set_max_tokens(config.max_tokens);
 
         let mut state = self
             .ctx
@@ -48,21 +233,52 @@ impl Transcriber {
             .full_n_segments()
             .map_err(|e| anyhow::anyhow!("Failed to get segments: {:?}", e))?;
 
+        let token_eot = self.ctx.token_eot();
         let mut text = String::new();
+        let mut segments = Vec::with_capacity(num_segments as usize);
         for i in 0..num_segments {
-            if let Ok(segment) = state.full_get_segment_text(i) {
-                text.push_str(&segment);
-            }
+            let Ok(segment) = state.full_get_segment_text(i) else {
+                continue;
+            };
+            text.push_str(&segment);
+
+            let n_tokens = state.full_n_tokens(i).unwrap_or(0);
+            let logprobs: Vec<f32> = (0..n_tokens)
+                .filter_map(|t| state.full_get_token_data(i, t).ok())
+                .filter(|data| data.id < token_eot)
+                .map(|data| data.plog)
+                .collect();
+            let avg_logprob = if logprobs.is_empty() {
+                0.0
+            } else {
+                logprobs.iter().sum::<f32>() / logprobs.len() as f32
+            };
+
+            segments.push(TranscriptSegment {
+                // Whisper timestamps are in 10 ms units
+                start_ms: state.full_get_segment_t0(i).unwrap_or(0) * 10,
+                end_ms: state.full_get_segment_t1(i).unwrap_or(0) * 10,
+                text: segment.trim().to_string(),
+                avg_logprob,
+            });
         }
 
-        Ok(text.trim().to_string())
+        let language = state
+            .full_lang_id_from_state()
+            .ok()
+            .and_then(whisper_rs::get_lang_str)
+            .map(str::to_string);
+
+        Ok(Transcription {
+            text: text.trim().to_string(),
+            segments,
+            language,
+            speech_ms: measure_speech_ms(audio),
+        })
     }
 
     pub fn default_model_dir() -> PathBuf {
-        dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".voxcode")
-            .join("models")
+        crate::settings::voxcode_dir().join("models")
     }
 
     pub fn default_model_path() -> PathBuf {
@@ -70,17 +286,57 @@ impl Transcriber {
     }
 }
 
-fn dirs_home_dir() -> Option<PathBuf> {
-    std::env::var("HOME").ok().map(PathBuf::from)
+impl SpeechToText for Transcriber {
+    fn transcribe(&self, audio: &[f32], options: &TranscribeOptions) -> Result<Transcription> {
+        Transcriber::transcribe(self, audio, options)
+    }
+
+    fn name(&self) -> String {
+        "whisper-rs".to_string()
+    }
+
+    fn set_decoding_config(&self, config: DecodingConfig) {
+        self.set_config(config);
+    }
+}
+
+/// Milliseconds of 16 kHz audio loud enough to be speech, in 30 ms frames.
+/// Unlike a share of the whole recording, this does not shrink when a short
+/// answer sits in a long listening window.
+pub fn measure_speech_ms(audio: &[f32]) -> u64 {
+    let mut vad = VoiceActivityDetector::new(SPEECH_RMS_THRESHOLD, 1);
+    let frames = audio
+        .chunks(SPEECH_FRAME_SAMPLES)
+        .filter(|chunk| vad.process(chunk).0)
+        .count();
+    frames as u64 * SPEECH_FRAME_MS
 }
 
-// Inline minimal dirs replacement
-mod dirs {
-    use std::path::PathBuf;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn home_dir() -> Option<PathBuf> {
-        std::env::var("HOME")
-            .ok()
-            .map(PathBuf::from)
+    #[test]
+    fn source_pass_detects_language_when_translating() {
+        let options = TranscribeOptions {
+            translate: true,
+            ..TranscribeOptions::default()
+        };
+        assert_eq!(
+            options.source(),
+            TranscribeOptions {
+                language: None,
+                translate: false,
+            }
+        );
+    }
+
+    #[test]
+    fn source_pass_keeps_language_otherwise() {
+        let options = TranscribeOptions {
+            language: Some("de".to_string()),
+            translate: false,
+        };
+        assert_eq!(options.source(), options);
     }
 }
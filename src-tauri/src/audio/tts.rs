@@ -0,0 +1,389 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tracing::{error, info, warn};
+
+use super::playback::AudioPlayer;
+use crate::sidecar::sdk::SdkMessage;
+
+/// Piper's output rate when the model config does not say otherwise.
+const PIPER_DEFAULT_RATE: u32 = 22050;
+/// espeak-ng's default speaking rate in words per minute.
+const ESPEAK_DEFAULT_WPM: f32 = 175.0;
+
+/// Local text-to-speech program, run once per utterance.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "engine", rename_all = "camelCase")]
+pub enum TtsEngine {
+    #[default]
+    EspeakNg,
+    /// Piper with an `.onnx` voice model. The sample rate is read from the
+    /// `.onnx.json` file next to it.
+    Piper { model: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TtsConfig {
+    /// Read assistant responses aloud.
+    pub enabled: bool,
+    pub engine: TtsEngine,
+    /// Executable to run instead of `espeak-ng`/`piper` on the PATH.
+    pub command: Option<String>,
+    /// espeak-ng voice name, or Piper speaker id for multi-speaker models.
+    pub voice: Option<String>,
+    /// Speaking rate relative to the engine default.
+    pub rate: f32,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            engine: TtsEngine::default(),
+            command: None,
+            voice: None,
+            rate: 1.0,
+        }
+    }
+}
+
+impl TtsConfig {
+    /// Synthesize `text` into mono samples and their sample rate.
+    pub fn synthesize(&self, text: &str) -> Result<(Vec<f32>, u32)> {
+        let rate = if self.rate > 0.0 { self.rate } else { 1.0 };
+        match &self.engine {
+            TtsEngine::EspeakNg => {
+                let mut cmd = Command::new(self.command.as_deref().unwrap_or("espeak-ng"));
+                cmd.arg("--stdout")
+                    .arg("--stdin")
+                    .arg("-s")
+                    .arg(((ESPEAK_DEFAULT_WPM * rate) as u32).to_string());
+                if let Some(voice) = &self.voice {
+                    cmd.arg("-v").arg(voice);
+                }
+                let wav = run(cmd, text)?;
+                parse_wav(&wav)
+            }
+            TtsEngine::Piper { model } => {
+                let mut cmd = Command::new(self.command.as_deref().unwrap_or("piper"));
+                cmd.arg("--model")
+                    .arg(model)
+                    .arg("--output_raw")
+                    .arg("--length_scale")
+                    .arg((1.0 / rate).to_string());
+                if let Some(speaker) = &self.voice {
+                    cmd.arg("--speaker").arg(speaker);
+                }
+                let raw = run(cmd, text)?;
+                Ok((pcm16_to_f32(&raw), piper_sample_rate(model)))
+            }
+        }
+    }
+}
+
+/// Run a TTS process with `text` on stdin and return its stdout.
+fn run(mut cmd: Command, text: &str) -> Result<Vec<u8>> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start {:?}", cmd.get_program()))?;
+
+    let mut stdin = child.stdin.take().context("Failed to get TTS stdin")?;
+    stdin.write_all(text.as_bytes())?;
+    drop(stdin);
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{:?} failed ({}): {}",
+            cmd.get_program(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+fn piper_sample_rate(model: &Path) -> u32 {
+    let mut config_path = model.as_os_str().to_owned();
+    config_path.push(".json");
+    std::fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|v| v["audio"]["sample_rate"].as_u64())
+        .map_or(PIPER_DEFAULT_RATE, |rate| rate as u32)
+}
+
+fn pcm16_to_f32(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+        .collect()
+}
+
+/// Decode a 16-bit PCM WAV, downmixing to mono. espeak-ng streams its WAV
+/// with a placeholder data length, so the data chunk runs to the end.
+fn parse_wav(bytes: &[u8]) -> Result<(Vec<f32>, u32)> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        anyhow::bail!("TTS output is not a WAV file");
+    }
+
+    let mut format: Option<(u16, u32, u16)> = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body = pos + 8;
+        if id == b"fmt " && body + 16 <= bytes.len() {
+            let channels = u16::from_le_bytes([bytes[body + 2], bytes[body + 3]]);
+            let rate = u32::from_le_bytes(bytes[body + 4..body + 8].try_into().unwrap());
+            let bits = u16::from_le_bytes([bytes[body + 14], bytes[body + 15]]);
+            format = Some((channels, rate, bits));
+        } else if id == b"data" {
+            let (channels, rate, bits) = format.context("WAV data before format")?;
+            if bits != 16 {
+                anyhow::bail!("Unsupported WAV sample size: {} bits", bits);
+            }
+            let end = body.saturating_add(len).min(bytes.len());
+            let samples = pcm16_to_f32(&bytes[body..end]);
+            let channels = channels.max(1) as usize;
+            let mono = samples
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect();
+            return Ok((mono, rate));
+        }
+        pos = body.saturating_add(len + (len & 1));
+    }
+    anyhow::bail!("WAV file has no data")
+}
+
+/// Reduce Markdown to something worth reading aloud: code blocks and tables
+/// are dropped, and emphasis, headings, list markers and link targets are
+/// stripped.
+pub fn speakable_text(markdown: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut in_code = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || trimmed.starts_with('|') || line.starts_with("    ") {
+            continue;
+        }
+
+        let mut text = trimmed.trim_start_matches(['#', '>']).trim_start();
+        for marker in ["- ", "* ", "+ "] {
+            if let Some(rest) = text.strip_prefix(marker) {
+                text = rest;
+            }
+        }
+        if let Some((number, rest)) = text.split_once(". ") {
+            if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+                text = rest;
+            }
+        }
+
+        let text = strip_links(text)
+            .replace("**", "")
+            .replace("__", "")
+            .replace('`', "");
+        if !text.is_empty() {
+            out.push(text);
+        }
+    }
+
+    out.join(" ")
+}
+
+/// `[label](target)` -> `label`.
+fn strip_links(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|i| open + i) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|i| close + i) else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        out.push_str(&rest[open + 1..close]);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+struct Utterance {
+    text: String,
+    generation: u64,
+}
+
+/// Reads text aloud one utterance at a time on a worker thread.
+pub struct Speaker {
+    config: Arc<RwLock<TtsConfig>>,
+    player: Arc<AudioPlayer>,
+    sender: Option<Sender<Utterance>>,
+    /// Bumped by `stop` so queued utterances are dropped.
+    generation: Arc<AtomicU64>,
+    /// Set by `skip` to cut the current utterance short.
+    skip: Arc<AtomicBool>,
+    /// Utterances queued or in progress.
+    pending: Arc<AtomicUsize>,
+}
+
+impl Speaker {
+    pub fn new(config: TtsConfig, player: Arc<AudioPlayer>) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            player,
+            sender: None,
+            generation: Arc::new(AtomicU64::new(0)),
+            skip: Arc::new(AtomicBool::new(false)),
+            pending: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Start the worker thread. Utterances queued before this are ignored.
+    pub fn spawn(&mut self, app_handle: AppHandle) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let worker = SpeakerWorker {
+            app_handle,
+            config: self.config.clone(),
+            player: self.player.clone(),
+            generation: self.generation.clone(),
+            skip: self.skip.clone(),
+            pending: self.pending.clone(),
+        };
+        std::thread::spawn(move || worker.run(receiver));
+        self.sender = Some(sender);
+    }
+
+    pub fn set_config(&self, config: TtsConfig) {
+        if !config.enabled {
+            self.stop();
+        }
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Speak the text of an assistant message. Tool calls, tool results
+    /// and subagent messages are left out.
+    pub fn speak_message(&self, message: &SdkMessage) {
+        if !self.config.read().unwrap().enabled {
+            return;
+        }
+        let SdkMessage::Assistant(message) = message else {
+            return;
+        };
+        if message.parent_tool_use_id().is_some() {
+            return;
+        }
+        if let Some(text) = message.text() {
+            self.speak(&speakable_text(&text));
+        }
+    }
+
+    /// Queue text to be spoken after anything already queued.
+    pub fn speak(&self, text: &str) {
+        let Some(sender) = &self.sender else {
+            warn!("Speech output not started");
+            return;
+        };
+        if text.trim().is_empty() {
+            return;
+        }
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        let _ = sender.send(Utterance {
+            text: text.to_string(),
+            generation: self.generation.load(Ordering::SeqCst),
+        });
+    }
+
+    /// Stop the current utterance and move on to the next one.
+    pub fn skip(&self) {
+        self.skip.store(true, Ordering::SeqCst);
+        self.player.stop();
+    }
+
+    /// Stop speaking and drop everything queued.
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.skip.store(true, Ordering::SeqCst);
+        self.player.stop();
+    }
+
+    /// Whether anything is being synthesized, played or waiting to be.
+    pub fn is_speaking(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0 || self.player.is_playing()
+    }
+}
+
+struct SpeakerWorker {
+    app_handle: AppHandle,
+    config: Arc<RwLock<TtsConfig>>,
+    player: Arc<AudioPlayer>,
+    generation: Arc<AtomicU64>,
+    skip: Arc<AtomicBool>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl SpeakerWorker {
+    fn run(self, receiver: Receiver<Utterance>) {
+        while let Ok(utterance) = receiver.recv() {
+            self.say(utterance);
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn say(&self, utterance: Utterance) {
+        if utterance.generation != self.generation.load(Ordering::SeqCst) {
+            return;
+        }
+        self.skip.store(false, Ordering::SeqCst);
+
+        let config = self.config.read().unwrap().clone();
+        let (samples, sample_rate) = match config.synthesize(&utterance.text) {
+            Ok(audio) => audio,
+            Err(e) => {
+                error!("Speech synthesis failed: {}", e);
+                let _ = self.app_handle.emit("speech-error", e.to_string());
+                return;
+            }
+        };
+        if utterance.generation != self.generation.load(Ordering::SeqCst)
+            || self.skip.load(Ordering::SeqCst)
+        {
+            return;
+        }
+
+        if let Err(e) = self.player.play(&samples, sample_rate) {
+            error!("Speech playback failed: {}", e);
+            let _ = self.app_handle.emit("speech-error", e.to_string());
+            return;
+        }
+        info!("Speaking {} chars", utterance.text.len());
+        let _ = self.app_handle.emit("speech-started", &utterance.text);
+        super::bargein::arm(&self.app_handle);
+
+        while self.player.is_playing() && !self.skip.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        super::bargein::disarm(&self.app_handle);
+        let _ = self.app_handle.emit("speech-finished", ());
+    }
+}
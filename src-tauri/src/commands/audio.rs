@@ -1,16 +1,24 @@
-use tauri::{AppHandle, State};
+use std::path::Path;
 
+use tauri::{AppHandle, Emitter, State};
+
+use crate::audio::file::{self, FileTranscript};
+use crate::audio::filter::TranscriptFilter;
+use crate::audio::transcribe::TranscribeOptions;
 use crate::error::VoxError;
 use crate::state::AppState;
+use crate::voice;
 
 #[tauri::command]
 pub fn start_recording(
     state: State<AppState>,
     app_handle: AppHandle,
 ) -> Result<(), VoxError> {
+    voice::cascade::discard_draft(&state);
+    let session_id = state.sessions.lock().unwrap().active_id().to_string();
     let mut audio = state.audio.lock().unwrap();
     audio
-        .start_recording(app_handle)
+        .start_recording(app_handle, &session_id)
         .map_err(|e: anyhow::Error| VoxError::Sidecar(e.to_string()))
 }
 
@@ -19,10 +27,7 @@ pub fn stop_recording(
     state: State<AppState>,
     app_handle: AppHandle,
 ) -> Result<String, VoxError> {
-    let mut audio = state.audio.lock().unwrap();
-    audio
-        .stop_recording(app_handle)
-        .map_err(|e: anyhow::Error| VoxError::Sidecar(e.to_string()))
+    voice::finish_recording(&state, &app_handle).map_err(|e| VoxError::Sidecar(e.to_string()))
 }
 
 #[tauri::command]
@@ -42,8 +47,52 @@ pub fn load_whisper_model(
     state: State<AppState>,
     model_path: String,
 ) -> Result<(), VoxError> {
+    let model_path = std::path::Path::new(&model_path);
+    let config = state.settings.lock().unwrap().decoding.for_model(model_path);
     let mut audio = state.audio.lock().unwrap();
     audio
-        .load_model(std::path::Path::new(&model_path))
+        .load_model(model_path, config)
         .map_err(|e: anyhow::Error| VoxError::Sidecar(e.to_string()))
 }
+
+#[tauri::command]
+pub async fn transcribe_file(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+    path: String,
+) -> Result<FileTranscript, VoxError> {
+    let session_id = state.sessions.lock().unwrap().active_id().to_string();
+    let (engine, options) = {
+        let audio = state.audio.lock().unwrap();
+        let engine = audio
+            .engine()
+            .ok_or_else(|| VoxError::Sidecar("Whisper model not loaded".to_string()))?;
+        (engine, audio.transcribe_options(&session_id))
+    };
+    let filter = TranscriptFilter::new(state.settings.lock().unwrap().filter.clone());
+
+    tokio::task::spawn_blocking(move || {
+        file::transcribe_file(Path::new(&path), engine.as_ref(), &options, &filter, |progress| {
+            let _ = app_handle.emit("file-transcription-progress", &progress);
+        })
+    })
+    .await
+    .map_err(|e| VoxError::Sidecar(e.to_string()))?
+    .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+#[tauri::command]
+pub fn get_transcribe_options(state: State<AppState>, session_id: String) -> TranscribeOptions {
+    let audio = state.audio.lock().unwrap();
+    audio.transcribe_options(&session_id)
+}
+
+#[tauri::command]
+pub fn set_transcribe_options(
+    state: State<AppState>,
+    session_id: String,
+    options: TranscribeOptions,
+) {
+    let mut audio = state.audio.lock().unwrap();
+    audio.set_transcribe_options(&session_id, options);
+}
@@ -1,34 +1,62 @@
 use tauri::State;
+use tracing::warn;
 
 use crate::error::VoxError;
+use crate::sidecar::manager::ACK_TIMEOUT;
 use crate::sidecar::protocol::ToSidecar;
 use crate::state::AppState;
+use crate::voice;
+
+/// Send a message to a session's sidecar and wait until it has accepted or
+/// rejected it.
+pub async fn send_to_sidecar(
+    state: &AppState,
+    session_id: &str,
+    msg: ToSidecar,
+) -> Result<(), VoxError> {
+    let sidecar = state
+        .sessions
+        .lock()
+        .unwrap()
+        .get(session_id)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))?;
+    let ack = sidecar
+        .send(&msg)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))?;
+    ack.wait(ACK_TIMEOUT)
+        .await
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
 
 #[tauri::command]
-pub fn send_message(
-    state: State<AppState>,
+pub async fn send_message(
+    state: State<'_, AppState>,
+    session_id: String,
     text: String,
     cwd: Option<String>,
 ) -> Result<(), VoxError> {
-    let sidecar = state.sidecar.lock().unwrap();
-    sidecar
-        .send(&ToSidecar::Send {
-            text,
-            cwd,
-        })
-        .map_err(|e| VoxError::Sidecar(e.to_string()))
+    voice::cascade::discard_draft(&state);
+    if let Err(e) = state
+        .corrections
+        .lock()
+        .unwrap()
+        .learn_from_sent(&session_id, &text)
+    {
+        warn!("Failed to update corrections: {}", e);
+    }
+
+    send_to_sidecar(&state, &session_id, ToSidecar::Send { text, cwd }).await
 }
 
 #[tauri::command]
-pub fn interrupt(state: State<AppState>) -> Result<(), VoxError> {
-    let sidecar = state.sidecar.lock().unwrap();
-    sidecar
-        .send(&ToSidecar::Interrupt)
-        .map_err(|e| VoxError::Sidecar(e.to_string()))
+pub async fn interrupt(state: State<'_, AppState>, session_id: String) -> Result<(), VoxError> {
+    send_to_sidecar(&state, &session_id, ToSidecar::Interrupt).await
 }
 
 #[tauri::command]
-pub fn is_sidecar_running(state: State<AppState>) -> bool {
-    let sidecar = state.sidecar.lock().unwrap();
-    sidecar.is_running()
+pub fn is_sidecar_running(state: State<AppState>, session_id: String) -> bool {
+    let sessions = state.sessions.lock().unwrap();
+    sessions
+        .get(&session_id)
+        .is_ok_and(|sidecar| sidecar.is_running())
 }
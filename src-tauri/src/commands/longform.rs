@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, State};
+
+use super::chat::send_to_sidecar;
+use crate::audio::earcons::{self, Earcon};
+use crate::audio::longform::{self, LongFormSegment};
+use crate::error::VoxError;
+use crate::sidecar::protocol::ToSidecar;
+use crate::state::AppState;
+
+#[tauri::command]
+pub fn start_long_form(
+    state: State<AppState>,
+    app_handle: AppHandle,
+    cwd: Option<String>,
+) -> Result<String, VoxError> {
+    let project_dir = match cwd {
+        Some(cwd) => PathBuf::from(cwd),
+        None => std::env::current_dir()?,
+    };
+    let config = state.settings.lock().unwrap().long_form.clone();
+    let session_id = state.sessions.lock().unwrap().active_id().to_string();
+    let mut audio = state.audio.lock().unwrap();
+    audio
+        .start_long_form(app_handle, &project_dir, &config, &session_id)
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+/// Stop long-form transcription and wait for the last chunk. Returns the
+/// transcript path, or `None` if no session was running.
+#[tauri::command]
+pub async fn stop_long_form(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<Option<String>, VoxError> {
+    let Some(session) = state.audio.lock().unwrap().stop_long_form() else {
+        return Ok(None);
+    };
+    earcons::cue(&app_handle, Earcon::RecordingStopped);
+    let path = tokio::task::spawn_blocking(move || session.finish())
+        .await
+        .map_err(|e| VoxError::Sidecar(e.to_string()))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+#[tauri::command]
+pub fn long_form_path(state: State<AppState>) -> Option<String> {
+    let audio = state.audio.lock().unwrap();
+    audio
+        .long_form_path()
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn read_long_form_transcript(path: String) -> Result<Vec<LongFormSegment>, VoxError> {
+    longform::read_transcript(Path::new(&path)).map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+/// Send the part of a transcript between `start_ms` and `end_ms` to the agent.
+#[tauri::command]
+pub async fn send_long_form_range(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    start_ms: i64,
+    end_ms: i64,
+    cwd: Option<String>,
+) -> Result<(), VoxError> {
+    let segments = longform::read_transcript(Path::new(&path))
+        .map_err(|e| VoxError::Sidecar(e.to_string()))?;
+    let excerpt = longform::transcript_range(&segments, start_ms, end_ms);
+    if excerpt.is_empty() {
+        return Err(VoxError::Sidecar(
+            "No transcript in the selected range".to_string(),
+        ));
+    }
+
+    let text = format!(
+        "Transcript excerpt ({}) from {}:\n\n{}",
+        longform::range_label(start_ms, end_ms),
+        path,
+        excerpt
+    );
+    send_to_sidecar(&state, &session_id, ToSidecar::Send { text, cwd }).await
+}
@@ -1,3 +1,9 @@
 pub mod audio;
 pub mod chat;
+pub mod longform;
 pub mod permissions;
+pub mod profiles;
+pub mod sessions;
+pub mod settings;
+pub mod speech;
+pub mod voice;
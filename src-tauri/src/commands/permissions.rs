@@ -1,31 +1,33 @@
 use tauri::State;
 
+use super::chat::send_to_sidecar;
 use crate::error::VoxError;
 use crate::sidecar::protocol::ToSidecar;
 use crate::state::AppState;
 
 #[tauri::command]
-pub fn respond_permission(
-    state: State<AppState>,
+pub async fn respond_permission(
+    state: State<'_, AppState>,
+    session_id: String,
     request_id: String,
     decision: String,
 ) -> Result<(), VoxError> {
-    let sidecar = state.sidecar.lock().unwrap();
-    sidecar
-        .send(&ToSidecar::RespondPermission {
+    send_to_sidecar(
+        &state,
+        &session_id,
+        ToSidecar::RespondPermission {
             request_id,
             decision,
-        })
-        .map_err(|e| VoxError::Sidecar(e.to_string()))
+        },
+    )
+    .await
 }
 
 #[tauri::command]
-pub fn set_permission_mode(
-    state: State<AppState>,
+pub async fn set_permission_mode(
+    state: State<'_, AppState>,
+    session_id: String,
     mode: String,
 ) -> Result<(), VoxError> {
-    let sidecar = state.sidecar.lock().unwrap();
-    sidecar
-        .send(&ToSidecar::SetPermissionMode { mode })
-        .map_err(|e| VoxError::Sidecar(e.to_string()))
+    send_to_sidecar(&state, &session_id, ToSidecar::SetPermissionMode { mode }).await
 }
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use tauri::{AppHandle, State};
+
+use crate::error::VoxError;
+use crate::sidecar::profiles::EnvProfile;
+use crate::state::AppState;
+
+#[tauri::command]
+pub fn list_env_profiles(state: State<AppState>) -> Vec<EnvProfile> {
+    state.profiles.lock().unwrap().list()
+}
+
+#[tauri::command]
+pub fn get_default_env_profile(state: State<AppState>) -> Option<String> {
+    state.profiles.lock().unwrap().default_profile()
+}
+
+/// Create or replace a profile. Secrets given as `null` keep their stored
+/// value. Sessions using the profile pick it up when their sidecar restarts.
+#[tauri::command]
+pub fn save_env_profile(
+    state: State<AppState>,
+    name: String,
+    env: BTreeMap<String, String>,
+    secrets: BTreeMap<String, Option<String>>,
+) -> Result<(), VoxError> {
+    state
+        .profiles
+        .lock()
+        .unwrap()
+        .save_profile(&name, env, secrets)
+        .map_err(|e| VoxError::Settings(e.to_string()))
+}
+
+#[tauri::command]
+pub fn delete_env_profile(state: State<AppState>, name: String) -> Result<(), VoxError> {
+    let users = state.sessions.lock().unwrap().using_profile(&name);
+    if !users.is_empty() {
+        return Err(VoxError::Settings(format!(
+            "Profile '{}' is used by session {}",
+            name,
+            users.join(", ")
+        )));
+    }
+    state
+        .profiles
+        .lock()
+        .unwrap()
+        .remove(&name)
+        .map_err(|e| VoxError::Settings(e.to_string()))
+}
+
+/// The profile for sessions opened without one. The default session gets
+/// it at the next app start.
+#[tauri::command]
+pub fn set_default_env_profile(
+    state: State<AppState>,
+    name: Option<String>,
+) -> Result<(), VoxError> {
+    state
+        .profiles
+        .lock()
+        .unwrap()
+        .set_default_profile(name)
+        .map_err(|e| VoxError::Settings(e.to_string()))
+}
+
+/// Switch a session's environment profile, restarting its sidecar.
+#[tauri::command]
+pub fn set_session_profile(
+    state: State<AppState>,
+    app_handle: AppHandle,
+    session_id: String,
+    profile: Option<String>,
+) -> Result<(), VoxError> {
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .set_profile(&app_handle, &session_id, profile)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
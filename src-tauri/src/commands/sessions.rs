@@ -0,0 +1,96 @@
+use tauri::{AppHandle, State};
+
+use crate::error::VoxError;
+use crate::sidecar::launch::{LaunchError, LaunchFailure, SidecarLaunch};
+use crate::sidecar::logs;
+use crate::sidecar::session::{self, SessionInfo};
+use crate::state::AppState;
+
+/// Start a sidecar for a new session, or update the working directory of
+/// an open one. `profile` defaults to the default environment profile.
+#[tauri::command]
+pub fn open_session(
+    state: State<AppState>,
+    app_handle: AppHandle,
+    session_id: String,
+    cwd: Option<String>,
+    profile: Option<String>,
+) -> Result<SessionInfo, VoxError> {
+    let mut sessions = state.sessions.lock().unwrap();
+    sessions
+        .open(&app_handle, &session_id, cwd, profile)
+        .and_then(|_| sessions.info(&session_id))
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+#[tauri::command]
+pub fn close_session(state: State<AppState>, session_id: String) -> Result<(), VoxError> {
+    let sidecar = state
+        .sessions
+        .lock()
+        .unwrap()
+        .close(&session_id)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))?;
+    // Released outside the lock, which recording and sending need meanwhile
+    tauri::async_runtime::spawn_blocking(move || drop(sidecar));
+    state.audio.lock().unwrap().forget_session(&session_id);
+    state
+        .corrections
+        .lock()
+        .unwrap()
+        .forget_session(&session_id);
+    Ok(())
+}
+
+/// Point voice commands, barge-in and speech at a session.
+#[tauri::command]
+pub fn set_active_session(state: State<AppState>, session_id: String) -> Result<(), VoxError> {
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .set_active(&session_id)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+#[tauri::command]
+pub fn get_session(state: State<AppState>, session_id: String) -> Result<SessionInfo, VoxError> {
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .info(&session_id)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+#[tauri::command]
+pub fn list_sessions(state: State<AppState>) -> Vec<SessionInfo> {
+    state.sessions.lock().unwrap().list()
+}
+
+/// The last `lines` lines of a session's sidecar log, e.g. for a bug
+/// report. Works for closed sessions too, until their logs expire.
+#[tauri::command]
+pub fn read_sidecar_log(
+    state: State<AppState>,
+    session_id: String,
+    lines: usize,
+) -> Result<Vec<String>, VoxError> {
+    let config = state.settings.lock().unwrap().sidecar_logs.clone();
+    logs::tail(&session_id, lines, &config).map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+/// Check the sidecar settings again, e.g. after fixing them, and start the
+/// default session if it was waiting for a usable sidecar. Open sessions
+/// keep the launch they were started with.
+#[tauri::command]
+pub async fn check_sidecar_launch(app_handle: AppHandle) -> Result<SidecarLaunch, LaunchFailure> {
+    tokio::task::spawn_blocking(move || session::refresh_launch(&app_handle))
+        .await
+        .unwrap_or_else(|e| {
+            Err(LaunchError::CheckFailed {
+                reason: e.to_string(),
+            })
+        })
+        .map_err(LaunchFailure::from)
+}
@@ -0,0 +1,46 @@
+use tauri::{AppHandle, State};
+
+use crate::audio;
+use crate::error::VoxError;
+use crate::settings::Settings;
+use crate::sidecar::session;
+use crate::state::AppState;
+
+#[tauri::command]
+pub fn get_settings(state: State<AppState>) -> Settings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn update_settings(
+    state: State<AppState>,
+    app_handle: AppHandle,
+    settings: Settings,
+) -> Result<(), VoxError> {
+    settings
+        .save()
+        .map_err(|e| VoxError::Settings(e.to_string()))?;
+
+    // Loading cascade models takes a while; recording must not wait for it
+    let cascade = audio::load_cascade(state.audio.lock().unwrap().cascade(), &settings);
+    let mut audio = state.audio.lock().unwrap();
+    audio.apply_settings(&settings);
+    audio.set_cascade(cascade);
+    drop(audio);
+    state
+        .corrections
+        .lock()
+        .unwrap()
+        .set_config(settings.corrections.clone());
+
+    state.speaker.lock().unwrap().set_config(settings.tts.clone());
+    state.cues.lock().unwrap().set_config(settings.earcons.clone());
+
+    let sidecar_changed = state.settings.lock().unwrap().sidecar != settings.sidecar;
+    *state.settings.lock().unwrap() = settings;
+    // Applies to sessions opened from now on
+    if sidecar_changed {
+        std::thread::spawn(move || session::refresh_launch(&app_handle));
+    }
+    Ok(())
+}
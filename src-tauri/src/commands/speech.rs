@@ -0,0 +1,26 @@
+use tauri::State;
+
+use crate::audio::tts;
+use crate::state::AppState;
+
+/// Read arbitrary text aloud, e.g. a message the user picked in the UI.
+#[tauri::command]
+pub fn speak_text(state: State<AppState>, text: String) {
+    let speaker = state.speaker.lock().unwrap();
+    speaker.speak(&tts::speakable_text(&text));
+}
+
+#[tauri::command]
+pub fn stop_speaking(state: State<AppState>) {
+    state.speaker.lock().unwrap().stop();
+}
+
+#[tauri::command]
+pub fn skip_speaking(state: State<AppState>) {
+    state.speaker.lock().unwrap().skip();
+}
+
+#[tauri::command]
+pub fn is_speaking(state: State<AppState>) -> bool {
+    state.speaker.lock().unwrap().is_speaking()
+}
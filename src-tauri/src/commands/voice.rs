@@ -0,0 +1,38 @@
+use tauri::{AppHandle, State};
+
+use crate::error::VoxError;
+use crate::state::AppState;
+use crate::voice;
+use crate::voice::corrections::Correction;
+use crate::voice::dictation::DictationMode;
+
+#[tauri::command]
+pub fn confirm_voice_command(
+    state: State<AppState>,
+    app_handle: AppHandle,
+    id: String,
+    confirmed: bool,
+) -> Result<(), VoxError> {
+    voice::confirm_command(&state, &app_handle, &id, confirmed)
+        .map_err(|e| VoxError::Sidecar(e.to_string()))
+}
+
+#[tauri::command]
+pub fn set_dictation_mode(state: State<AppState>, mode: DictationMode) -> Result<(), VoxError> {
+    voice::set_dictation_mode(&state, mode).map_err(|e| VoxError::Settings(e.to_string()))
+}
+
+#[tauri::command]
+pub fn get_corrections(state: State<AppState>) -> Vec<Correction> {
+    state.corrections.lock().unwrap().list()
+}
+
+#[tauri::command]
+pub fn remove_correction(state: State<AppState>, from: String) -> Result<(), VoxError> {
+    state
+        .corrections
+        .lock()
+        .unwrap()
+        .remove(&from)
+        .map_err(|e| VoxError::Settings(e.to_string()))
+}
@@ -4,6 +4,8 @@ use serde::Serialize;
 pub enum VoxError {
     #[error("Sidecar error: {0}")]
     Sidecar(String),
+    #[error("Settings error: {0}")]
+    Settings(String),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     #[error("JSON error: {0}")]
@@ -1,10 +1,12 @@
 mod audio;
 mod commands;
 mod error;
+mod settings;
 mod sidecar;
 mod state;
+mod voice;
 
-use tauri::Manager;
+use tauri::{Manager, RunEvent};
 use state::AppState;
 use tracing::info;
 
@@ -23,22 +25,24 @@ pub fn run() {
         .setup(|app| {
             let app_handle = app.handle().clone();
 
-            // Resolve sidecar path
-            let sidecar_path = resolve_sidecar_path(&app_handle);
-            info!("Sidecar path: {}", sidecar_path);
+            // Check the sidecar and spawn it for the default session. The
+            // runtime check may take a while, so the window opens meanwhile.
+            std::thread::spawn(move || sidecar::session::refresh_launch(&app_handle));
 
-            // Spawn the sidecar
             let state = app.state::<AppState>();
-            let sidecar = state.sidecar.lock().unwrap();
-            if let Err(e) = sidecar.spawn(&sidecar_path, app_handle) {
-                tracing::error!("Failed to spawn sidecar: {}", e);
-            }
+            state.speaker.lock().unwrap().spawn(app.handle().clone());
+
+            let settings = state.settings.lock().unwrap().clone();
+            sidecar::logs::prune(&settings.sidecar_logs);
+            let mut audio = state.audio.lock().unwrap();
+            audio.apply_settings(&settings);
+            audio.set_cascade(audio::load_cascade(None, &settings));
 
             // Try to load Whisper model if it exists
             let model_path = audio::transcribe::Transcriber::default_model_path();
             if model_path.exists() {
-                let mut audio = state.audio.lock().unwrap();
-                match audio.load_model(&model_path) {
+                let config = settings.decoding.for_model(&model_path);
+                match audio.load_model(&model_path, config) {
                     Ok(()) => info!("Whisper model loaded from {}", model_path.display()),
                     Err(e) => tracing::warn!("Failed to load Whisper model: {}", e),
                 }
@@ -57,37 +61,48 @@ pub fn run() {
             commands::chat::is_sidecar_running,
             commands::permissions::respond_permission,
             commands::permissions::set_permission_mode,
+            commands::sessions::open_session,
+            commands::sessions::close_session,
+            commands::sessions::set_active_session,
+            commands::sessions::get_session,
+            commands::sessions::list_sessions,
+            commands::sessions::read_sidecar_log,
+            commands::sessions::check_sidecar_launch,
+            commands::profiles::list_env_profiles,
+            commands::profiles::get_default_env_profile,
+            commands::profiles::save_env_profile,
+            commands::profiles::delete_env_profile,
+            commands::profiles::set_default_env_profile,
+            commands::profiles::set_session_profile,
             commands::audio::start_recording,
             commands::audio::stop_recording,
             commands::audio::is_recording,
             commands::audio::is_model_loaded,
             commands::audio::load_whisper_model,
+            commands::audio::transcribe_file,
+            commands::audio::get_transcribe_options,
+            commands::audio::set_transcribe_options,
+            commands::longform::start_long_form,
+            commands::longform::stop_long_form,
+            commands::longform::long_form_path,
+            commands::longform::read_long_form_transcript,
+            commands::longform::send_long_form_range,
+            commands::speech::speak_text,
+            commands::speech::stop_speaking,
+            commands::speech::skip_speaking,
+            commands::speech::is_speaking,
+            commands::settings::get_settings,
+            commands::settings::update_settings,
+            commands::voice::confirm_voice_command,
+            commands::voice::set_dictation_mode,
+            commands::voice::get_corrections,
+            commands::voice::remove_correction,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
-
-fn resolve_sidecar_path(app_handle: &tauri::AppHandle) -> String {
-    // In development, use the sidecar from the project directory
-    let dev_path = std::env::current_dir()
-        .ok()
-        .map(|p| p.join("sidecar").join("dist").join("voxcode-sidecar"));
-
-    if let Some(path) = dev_path {
-        if path.exists() {
-            return path.to_string_lossy().to_string();
-        }
-    }
-
-    // In production, resolve from the app's resource directory
-    let resource_dir = app_handle.path().resource_dir().ok();
-    if let Some(dir) = resource_dir {
-        let path = dir.join("binaries").join("voxcode-sidecar");
-        if path.exists() {
-            return path.to_string_lossy().to_string();
-        }
-    }
-
-    // Fallback: try node directly (for development without compiled sidecar)
-    "node".to_string()
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                sidecar::session::shut_down_all(app_handle);
+            }
+        });
 }
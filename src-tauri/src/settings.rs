@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::audio::aec::AecConfig;
+use crate::audio::bargein::BargeInConfig;
+use crate::audio::earcons::EarconConfig;
+use crate::audio::filter::FilterConfig;
+use crate::audio::longform::LongFormConfig;
+use crate::audio::stt::SttBackend;
+use crate::audio::transcribe::{DecodingConfig, Transcriber};
+use crate::audio::tts::TtsConfig;
+use crate::sidecar::launch::SidecarLaunchConfig;
+use crate::sidecar::logs::SidecarLogConfig;
+use crate::voice::corrections::CorrectionConfig;
+use crate::voice::dictation::DictationConfig;
+use crate::voice::grammar::CommandGrammar;
+use crate::voice::permission::SpokenPermissionConfig;
+
+/// Root of VoxCode's per-user data (`~/.voxcode`).
+pub fn voxcode_dir() -> PathBuf {
+    std::env::var("HOME")
+        .ok()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".voxcode")
+}
+
+/// Backend settings, persisted as JSON in `~/.voxcode/settings.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub stt: SttBackend,
+    pub decoding: DecodingSettings,
+    pub filter: FilterConfig,
+    pub voice_commands: CommandGrammar,
+    pub dictation: DictationConfig,
+    pub corrections: CorrectionConfig,
+    pub cascade: CascadeConfig,
+    pub long_form: LongFormConfig,
+    pub tts: TtsConfig,
+    pub barge_in: BargeInConfig,
+    pub echo_cancellation: AecConfig,
+    pub earcons: EarconConfig,
+    pub spoken_permissions: SpokenPermissionConfig,
+    pub sidecar: SidecarLaunchConfig,
+    pub sidecar_logs: SidecarLogConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DecodingSettings {
+    /// Used for any model without an entry in `per_model`.
+    pub default: DecodingConfig,
+    /// Overrides keyed by model file name, e.g. `ggml-small.en.bin`.
+    pub per_model: HashMap<String, DecodingConfig>,
+}
+
+/// Two-pass transcription: `draft_model` produces a quick draft and
+/// `refine_model` re-transcribes the same audio in the background.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CascadeConfig {
+    pub enabled: bool,
+    pub draft_model: PathBuf,
+    pub refine_model: PathBuf,
+}
+
+impl Default for CascadeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            draft_model: Transcriber::default_model_dir().join("ggml-tiny.en.bin"),
+            refine_model: Transcriber::default_model_dir().join("ggml-small.en.bin"),
+        }
+    }
+}
+
+impl DecodingSettings {
+    pub fn for_model(&self, model_path: &Path) -> DecodingConfig {
+        model_path
+            .file_name()
+            .and_then(|name| self.per_model.get(&*name.to_string_lossy()))
+            .unwrap_or(&self.default)
+            .clone()
+    }
+}
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        voxcode_dir().join("settings.json")
+    }
+
+    /// Load settings from disk, falling back to defaults if the file is
+    /// missing or unreadable.
+    pub fn load() -> Self {
+        let path = Self::path();
+        if !path.exists() {
+            return Self::default();
+        }
+        match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| serde_json::from_str(&s).map_err(anyhow::Error::from))
+        {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("Failed to read settings from {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,576 @@
+//! How the sidecar is started: the `sidecar` settings, or the compiled
+//! sidecar found next to the app, checked before anything is spawned so a
+//! missing binary or runtime is reported as such.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+const SIDECAR_NAME: &str = "voxcode-sidecar";
+/// How long a runtime gets to print its version.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SidecarLaunchConfig {
+    /// A compiled sidecar, a script, or `node`/`bun` with the script in
+    /// `args`. Bare names are looked up on `PATH`. Unset means the compiled
+    /// sidecar from the dev build or the app bundle.
+    pub executable: Option<PathBuf>,
+    pub args: Vec<String>,
+    /// Set on top of the app's environment.
+    pub env: HashMap<String, String>,
+    /// Where the sidecar runs; relative paths above are resolved against it.
+    pub working_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RuntimeKind {
+    Node,
+    Bun,
+}
+
+impl RuntimeKind {
+    fn of_program(program: &Path) -> Option<Self> {
+        match program.file_stem()?.to_str()? {
+            "node" => Some(Self::Node),
+            "bun" => Some(Self::Bun),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Node => "node",
+            Self::Bun => "bun",
+        }
+    }
+
+    /// Oldest version the Agent SDK runs on.
+    fn minimum(self) -> [u32; 3] {
+        match self {
+            Self::Node => [18, 0, 0],
+            Self::Bun => [1, 0, 0],
+        }
+    }
+
+    /// Options that take the next argument as their value, which must not
+    /// be mistaken for the script.
+    fn value_options(self) -> &'static [&'static str] {
+        match self {
+            Self::Node => &[
+                "-r",
+                "--require",
+                "--import",
+                "--loader",
+                "--experimental-loader",
+                "-C",
+                "--conditions",
+                "--env-file",
+                "--input-type",
+                "--title",
+            ],
+            Self::Bun => &[
+                "--cwd",
+                "-r",
+                "--preload",
+                "-c",
+                "--config",
+                "--env-file",
+                "--tsconfig-override",
+                "-d",
+                "--define",
+                "-l",
+                "--loader",
+                "--conditions",
+                "--main-fields",
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Runtime {
+    pub kind: RuntimeKind,
+    pub version: String,
+}
+
+/// A checked way to start the sidecar.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarLaunch {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub working_dir: Option<PathBuf>,
+    /// Set when `program` is a JavaScript runtime running a script.
+    pub runtime: Option<Runtime>,
+}
+
+impl fmt::Display for SidecarLaunch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program.display())?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum LaunchError {
+    #[error(
+        "No sidecar found; build it with `bun run build` in sidecar/ or set an executable in the sidecar settings (looked for {})",
+        display_paths(.searched)
+    )]
+    NotFound { searched: Vec<PathBuf> },
+    #[error("Sidecar executable {} does not exist", .path.display())]
+    MissingExecutable { path: PathBuf },
+    #[error("Sidecar executable {name} is not on PATH")]
+    NotOnPath { name: String },
+    #[error("Sidecar executable {} is not executable", .path.display())]
+    NotExecutable { path: PathBuf },
+    #[error("{runtime} needs a sidecar script to run; add it to the sidecar arguments")]
+    MissingScript { runtime: String },
+    #[error("Sidecar script {} does not exist", .path.display())]
+    ScriptNotFound { path: PathBuf },
+    #[error("Sidecar working directory {} does not exist", .path.display())]
+    MissingWorkingDir { path: PathBuf },
+    #[error("{runtime} is needed to run the sidecar but could not be used: {reason}")]
+    RuntimeUnavailable { runtime: String, reason: String },
+    #[error("{runtime} {found} is too old for the sidecar; install {required} or newer")]
+    RuntimeTooOld {
+        runtime: String,
+        found: String,
+        required: String,
+    },
+    #[error("Checking the sidecar failed: {reason}")]
+    CheckFailed { reason: String },
+}
+
+/// A `LaunchError` as the UI receives it: the kind and its details, plus
+/// the message to show.
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchFailure {
+    pub message: String,
+    #[serde(flatten)]
+    pub error: LaunchError,
+}
+
+impl From<LaunchError> for LaunchFailure {
+    fn from(error: LaunchError) -> Self {
+        Self {
+            message: error.to_string(),
+            error,
+        }
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Resolve the launch configured in settings, with the app's resource
+/// directory as a place to find the bundled sidecar.
+pub fn resolve_for_app(
+    app_handle: &AppHandle,
+    config: &SidecarLaunchConfig,
+) -> Result<SidecarLaunch, LaunchError> {
+    let resource_dir = app_handle.path().resource_dir().ok();
+    resolve(config, resource_dir.as_deref())
+}
+
+pub fn resolve(
+    config: &SidecarLaunchConfig,
+    resource_dir: Option<&Path>,
+) -> Result<SidecarLaunch, LaunchError> {
+    let base_dir = match &config.working_dir {
+        Some(dir) if !dir.is_dir() => {
+            return Err(LaunchError::MissingWorkingDir { path: dir.clone() })
+        }
+        Some(dir) => Some(dir.clone()),
+        None => std::env::current_dir().ok(),
+    };
+
+    let (program, mut args) = match &config.executable {
+        Some(executable) => (
+            find_program(executable, base_dir.as_deref())?,
+            config.args.clone(),
+        ),
+        None => (default_sidecar(resource_dir)?, config.args.clone()),
+    };
+
+    // A script is handed to a runtime as its first argument
+    let program = match script_runtime(&program) {
+        Some(candidates) => {
+            args.insert(0, program.to_string_lossy().to_string());
+            find_runtime(candidates)?
+        }
+        None => program,
+    };
+
+    let runtime = match RuntimeKind::of_program(&program) {
+        Some(kind) => {
+            check_script(kind, &args, base_dir.as_deref())?;
+            Some(check_runtime(kind, &program)?)
+        }
+        None => {
+            check_executable(&program)?;
+            None
+        }
+    };
+
+    Ok(SidecarLaunch {
+        program,
+        args,
+        env: config.env.clone(),
+        working_dir: config.working_dir.clone(),
+        runtime,
+    })
+}
+
+/// The compiled sidecar from a dev build or the app bundle, or the sidecar
+/// source when developing without a build.
+fn default_sidecar(resource_dir: Option<&Path>) -> Result<PathBuf, LaunchError> {
+    let dev_dir = std::env::current_dir().ok().map(|dir| dir.join("sidecar"));
+    let candidates: Vec<PathBuf> = [
+        dev_dir
+            .as_ref()
+            .map(|dir| dir.join("dist").join(SIDECAR_NAME)),
+        resource_dir.map(|dir| dir.join("binaries").join(SIDECAR_NAME)),
+        dev_dir.as_ref().map(|dir| dir.join("src").join("index.ts")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(LaunchError::NotFound {
+            searched: candidates,
+        }),
+    }
+}
+
+fn find_program(executable: &Path, base_dir: Option<&Path>) -> Result<PathBuf, LaunchError> {
+    // A bare name, as a shell would look it up; scripts are files
+    let bare = executable.components().count() == 1 && !executable.is_absolute();
+    if bare && script_runtime(executable).is_none() {
+        let name = executable.to_string_lossy().to_string();
+        return find_in_path(&name).ok_or(LaunchError::NotOnPath { name });
+    }
+    let path = match base_dir {
+        Some(dir) if executable.is_relative() => dir.join(executable),
+        _ => executable.to_path_buf(),
+    };
+    if !path.is_file() {
+        return Err(LaunchError::MissingExecutable { path });
+    }
+    Ok(path)
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|dir| {
+            let plain = dir.join(name);
+            let exe = dir.join(format!("{}.exe", name));
+            [plain, exe]
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+/// Runtimes that can run `program`, if it is a script rather than a binary.
+fn script_runtime(program: &Path) -> Option<&'static [RuntimeKind]> {
+    match program.extension()?.to_str()? {
+        "ts" | "tsx" => Some(&[RuntimeKind::Bun]),
+        "js" | "mjs" | "cjs" => Some(&[RuntimeKind::Bun, RuntimeKind::Node]),
+        _ => None,
+    }
+}
+
+fn find_runtime(candidates: &[RuntimeKind]) -> Result<PathBuf, LaunchError> {
+    candidates
+        .iter()
+        .find_map(|kind| find_in_path(kind.name()))
+        .ok_or_else(|| LaunchError::RuntimeUnavailable {
+            runtime: candidates
+                .iter()
+                .map(|kind| kind.name())
+                .collect::<Vec<_>>()
+                .join(" or "),
+            reason: "not found on PATH".to_string(),
+        })
+}
+
+/// A runtime without a script reads one from stdin, i.e. from our protocol
+/// messages, so the script has to be there.
+fn check_script(
+    kind: RuntimeKind,
+    args: &[String],
+    base_dir: Option<&Path>,
+) -> Result<(), LaunchError> {
+    let mut dir = base_dir.map(Path::to_path_buf);
+    let mut script = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (option, value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with('-') => (option, Some(value)),
+            _ if kind.value_options().contains(&arg.as_str()) => {
+                (arg.as_str(), args.next().map(String::as_str))
+            }
+            _ if arg.starts_with('-') || arg == "run" => continue,
+            _ => {
+                script = Some(arg);
+                break;
+            }
+        };
+        // Bun looks for the script in the directory it changes to
+        if let (RuntimeKind::Bun, "--cwd", Some(cwd)) = (kind, option, value) {
+            dir = Some(match dir {
+                Some(dir) => dir.join(cwd),
+                None => PathBuf::from(cwd),
+            });
+        }
+    }
+
+    let script = script.ok_or_else(|| LaunchError::MissingScript {
+        runtime: kind.name().to_string(),
+    })?;
+    let path = match dir {
+        Some(dir) => dir.join(script),
+        None => PathBuf::from(script),
+    };
+    if !path.is_file() {
+        return Err(LaunchError::ScriptNotFound { path });
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn check_executable(program: &Path) -> Result<(), LaunchError> {
+    use std::os::unix::fs::PermissionsExt;
+    let executable = program
+        .metadata()
+        .is_ok_and(|m| m.permissions().mode() & 0o111 != 0);
+    if !executable {
+        return Err(LaunchError::NotExecutable {
+            path: program.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_executable(_program: &Path) -> Result<(), LaunchError> {
+    Ok(())
+}
+
+fn check_runtime(kind: RuntimeKind, program: &Path) -> Result<Runtime, LaunchError> {
+    let unavailable = |reason: String| LaunchError::RuntimeUnavailable {
+        runtime: kind.name().to_string(),
+        reason,
+    };
+    let version = runtime_version(program).map_err(unavailable)?;
+    let parsed = parse_version(&version)
+        .ok_or_else(|| unavailable(format!("unexpected version '{}'", version)))?;
+    let minimum = kind.minimum();
+    if parsed < minimum {
+        return Err(LaunchError::RuntimeTooOld {
+            runtime: kind.name().to_string(),
+            found: version,
+            required: format!("{}.{}.{}", minimum[0], minimum[1], minimum[2]),
+        });
+    }
+    Ok(Runtime { kind, version })
+}
+
+/// `program --version`, giving up on a runtime that hangs.
+fn runtime_version(program: &Path) -> Result<String, String> {
+    let mut child = Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let deadline = Instant::now() + VERSION_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(Some(status)) => return Err(format!("--version failed with {}", status)),
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "no answer to --version within {:?}",
+                    VERSION_TIMEOUT
+                ));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout
+            .read_to_string(&mut output)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(output.trim().to_string())
+}
+
+/// `v20.11.1` or `1.1.8-canary.1` as `[major, minor, patch]`.
+fn parse_version(version: &str) -> Option<[u32; 3]> {
+    let mut parts = version.trim_start_matches('v').split('.').map(|part| {
+        let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse::<u32>().ok()
+    });
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some([major, minor, patch])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_runtime_versions() {
+        assert_eq!(parse_version("v20.11.1"), Some([20, 11, 1]));
+        assert_eq!(parse_version("1.1.38"), Some([1, 1, 38]));
+        assert_eq!(parse_version("22"), Some([22, 0, 0]));
+        assert_eq!(parse_version("1.2.0-canary.5"), Some([1, 2, 0]));
+        assert_eq!(parse_version("v18.0.0-nightly"), Some([18, 0, 0]));
+        assert_eq!(parse_version("unknown"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn old_versions_compare_below_the_minimum() {
+        assert!(parse_version("v16.20.2").unwrap() < RuntimeKind::Node.minimum());
+        assert!(parse_version("v18.0.0").unwrap() >= RuntimeKind::Node.minimum());
+        assert!(parse_version("0.8.1").unwrap() < RuntimeKind::Bun.minimum());
+    }
+
+    #[test]
+    fn recognizes_runtimes_and_scripts() {
+        assert_eq!(
+            RuntimeKind::of_program(Path::new("/usr/bin/node")),
+            Some(RuntimeKind::Node)
+        );
+        assert_eq!(
+            RuntimeKind::of_program(Path::new("/opt/bun/bun.exe")),
+            Some(RuntimeKind::Bun)
+        );
+        assert_eq!(RuntimeKind::of_program(Path::new("/bin/sidecar")), None);
+        assert_eq!(
+            script_runtime(Path::new("index.ts")),
+            Some(&[RuntimeKind::Bun][..])
+        );
+        assert_eq!(
+            script_runtime(Path::new("dist/index.mjs")),
+            Some(&[RuntimeKind::Bun, RuntimeKind::Node][..])
+        );
+        assert_eq!(script_runtime(Path::new("voxcode-sidecar")), None);
+    }
+
+    #[test]
+    fn script_must_be_given_and_exist() {
+        let dir = std::env::temp_dir().join(format!("voxcode-launch-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("index.ts"), "").unwrap();
+
+        assert_eq!(
+            check_script(
+                RuntimeKind::Bun,
+                &args(&["run", "--smol", "index.ts"]),
+                Some(&dir)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_script(RuntimeKind::Bun, &args(&["run", "--smol"]), Some(&dir)),
+            Err(LaunchError::MissingScript {
+                runtime: "bun".to_string()
+            })
+        );
+        assert_eq!(
+            check_script(RuntimeKind::Node, &args(&["main.js"]), Some(&dir)),
+            Err(LaunchError::ScriptNotFound {
+                path: dir.join("main.js")
+            })
+        );
+        // A directory is not a script
+        fs::create_dir(dir.join("src")).unwrap();
+        assert!(check_script(RuntimeKind::Node, &args(&["src"]), Some(&dir)).is_err());
+
+        // Option values are not the script
+        fs::write(dir.join("src/main.js"), "").unwrap();
+        assert_eq!(
+            check_script(
+                RuntimeKind::Node,
+                &args(&["--require", "dotenv/config", "src/main.js"]),
+                Some(&dir)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_script(RuntimeKind::Node, &args(&["-r", "missing.js"]), Some(&dir)),
+            Err(LaunchError::MissingScript {
+                runtime: "node".to_string()
+            })
+        );
+        // Bun resolves the script against --cwd
+        for options in [&["--cwd", "src"][..], &["--cwd=src"]] {
+            let mut bun_args = options.to_vec();
+            bun_args.extend(["run", "main.js"]);
+            assert_eq!(
+                check_script(RuntimeKind::Bun, &args(&bun_args), Some(&dir)),
+                Ok(())
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failures_carry_kind_and_message() {
+        let failure = LaunchFailure::from(LaunchError::RuntimeTooOld {
+            runtime: "node".to_string(),
+            found: "v16.0.0".to_string(),
+            required: "18.0.0".to_string(),
+        });
+        let json = serde_json::to_value(&failure).unwrap();
+        assert_eq!(json["kind"], "runtimeTooOld");
+        assert_eq!(json["found"], "v16.0.0");
+        assert_eq!(
+            json["message"],
+            "node v16.0.0 is too old for the sidecar; install 18.0.0 or newer"
+        );
+    }
+}
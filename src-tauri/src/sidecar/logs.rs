@@ -0,0 +1,328 @@
+//! Per-session sidecar logs in `~/.voxcode/logs/sidecar`, so the sidecar's
+//! stderr outlives the process and can be attached to bug reports.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::settings::voxcode_dir;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SidecarLogConfig {
+    /// Size at which a session's log is rotated.
+    pub max_file_bytes: u64,
+    /// Rotated files kept per session besides the current one.
+    pub max_rotated_files: usize,
+    /// Logs not written to for this long are deleted at startup; 0 keeps
+    /// them forever.
+    pub retention_days: u64,
+}
+
+impl Default for SidecarLogConfig {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 1024 * 1024,
+            max_rotated_files: 3,
+            retention_days: 14,
+        }
+    }
+}
+
+pub fn log_dir() -> PathBuf {
+    voxcode_dir().join("logs").join("sidecar")
+}
+
+/// `<session>.log` for `index` 0, then `<session>.1.log` for the newest
+/// rotated file and so on. Session ids come from the frontend, so anything
+/// unusual is percent-escaped, which keeps distinct ids apart.
+fn log_path(dir: &Path, session_id: &str, index: usize) -> PathBuf {
+    let mut stem = String::with_capacity(session_id.len());
+    for byte in session_id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            stem.push(byte as char);
+        } else {
+            let _ = write!(stem, "%{:02X}", byte);
+        }
+    }
+    match index {
+        0 => dir.join(format!("{}.log", stem)),
+        n => dir.join(format!("{}.{}.log", stem, n)),
+    }
+}
+
+/// The open log of one session, appended to across sidecar restarts.
+/// Lines are written and rotated on a thread of its own, so logging never
+/// holds up the stderr reader on the disk.
+pub struct SidecarLog {
+    entries: mpsc::Sender<String>,
+}
+
+impl SidecarLog {
+    pub fn open(session_id: &str, config: SidecarLogConfig) -> Result<Self> {
+        let mut file = LogFile::open_in(log_dir(), session_id, config)?;
+        let (entries, receiver) = mpsc::channel::<String>();
+        // Ends once the log is dropped and everything queued is written
+        thread::Builder::new()
+            .name("sidecar-log".to_string())
+            .spawn(move || {
+                for entry in receiver {
+                    file.append(&entry);
+                }
+            })
+            .context("Failed to start the sidecar log writer")?;
+        Ok(Self { entries })
+    }
+
+    /// Queue a timestamped line.
+    pub fn write_line(&self, line: &str) {
+        let _ = self.entries.send(entry(line));
+    }
+}
+
+fn entry(line: &str) -> String {
+    format!(
+        "{} {}\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        line
+    )
+}
+
+/// A session's current log file, rotated when it grows too large.
+struct LogFile {
+    dir: PathBuf,
+    session_id: String,
+    config: SidecarLogConfig,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open_in(dir: PathBuf, session_id: &str, config: SidecarLogConfig) -> Result<Self> {
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = log_path(&dir, session_id, 0);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir,
+            session_id: session_id.to_string(),
+            config,
+            file,
+            size,
+        })
+    }
+
+    /// Append an entry, rotating first if it would not fit.
+    fn append(&mut self, entry: &str) {
+        if self.size > 0 && self.size + entry.len() as u64 > self.config.max_file_bytes {
+            if let Err(e) = self.rotate() {
+                warn!("Failed to rotate sidecar log: {}", e);
+            }
+        }
+        match self.file.write_all(entry.as_bytes()) {
+            Ok(()) => self.size += entry.len() as u64,
+            Err(e) => warn!("Failed to write sidecar log: {}", e),
+        }
+    }
+
+    /// Shift every rotated file up by one, dropping the oldest, and start
+    /// a new current file.
+    fn rotate(&mut self) -> Result<()> {
+        let keep = self.config.max_rotated_files;
+        remove_if_exists(&log_path(&self.dir, &self.session_id, keep))?;
+        for index in (0..keep).rev() {
+            let from = log_path(&self.dir, &self.session_id, index);
+            if from.exists() {
+                let to = log_path(&self.dir, &self.session_id, index + 1);
+                fs::rename(&from, &to)
+                    .with_context(|| format!("Failed to rename {}", from.display()))?;
+            }
+        }
+
+        let path = log_path(&self.dir, &self.session_id, 0);
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The last `lines` lines logged for a session, oldest first, reading back
+/// through the rotated files as needed.
+pub fn tail(session_id: &str, lines: usize, config: &SidecarLogConfig) -> Result<Vec<String>> {
+    tail_in(&log_dir(), session_id, lines, config)
+}
+
+fn tail_in(
+    dir: &Path,
+    session_id: &str,
+    lines: usize,
+    config: &SidecarLogConfig,
+) -> Result<Vec<String>> {
+    let mut tail = VecDeque::new();
+    for index in 0..=config.max_rotated_files {
+        if tail.len() >= lines {
+            break;
+        }
+        let path = log_path(dir, session_id, index);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            // Rotated files are numbered without gaps
+            Err(e) if e.kind() == ErrorKind::NotFound => break,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let wanted = lines - tail.len();
+        for line in String::from_utf8_lossy(&bytes).lines().rev().take(wanted) {
+            tail.push_front(line.to_string());
+        }
+    }
+    Ok(tail.into())
+}
+
+/// Delete logs not written to within the retention period, e.g. those of
+/// sessions closed long ago.
+pub fn prune(config: &SidecarLogConfig) {
+    if config.retention_days == 0 {
+        return;
+    }
+    let max_age = Duration::from_secs(config.retention_days * 24 * 60 * 60);
+    let Ok(entries) = fs::read_dir(log_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("log") {
+            continue;
+        }
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if !expired {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => info!("Removed old sidecar log {}", path.display()),
+            Err(e) => warn!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("voxcode-logs-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn config(max_file_bytes: u64, max_rotated_files: usize) -> SidecarLogConfig {
+        SidecarLogConfig {
+            max_file_bytes,
+            max_rotated_files,
+            ..SidecarLogConfig::default()
+        }
+    }
+
+    fn write_line(file: &mut LogFile, line: &str) {
+        file.append(&entry(line));
+    }
+
+    /// Log lines without their timestamp.
+    fn messages(lines: Vec<String>) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.splitn(3, ' ').nth(2).unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn log_paths_are_numbered_and_escaped() {
+        let dir = Path::new("/logs");
+        assert_eq!(log_path(dir, "default", 0), dir.join("default.log"));
+        assert_eq!(log_path(dir, "default", 2), dir.join("default.2.log"));
+        assert_eq!(log_path(dir, "../a b", 0), dir.join("%2E%2E%2Fa%20b.log"));
+        assert_eq!(log_path(dir, "a_b", 0), dir.join("a_b.log"));
+        assert_ne!(log_path(dir, "a/b", 0), log_path(dir, "a_b", 0));
+        assert_ne!(log_path(dir, "a%2Fb", 0), log_path(dir, "a/b", 0));
+        assert_ne!(log_path(dir, "a.1", 0), log_path(dir, "a", 1));
+    }
+
+    #[test]
+    fn rotates_and_drops_the_oldest_file() {
+        let dir = temp_dir();
+        // Each entry is 24 bytes of timestamp plus "line N\n", so two fit
+        let config = config(64, 2);
+        let mut log = LogFile::open_in(dir.clone(), "s", config.clone()).unwrap();
+        for n in 0..8 {
+            write_line(&mut log, &format!("line {}", n));
+        }
+
+        assert!(log_path(&dir, "s", 2).exists());
+        assert!(!log_path(&dir, "s", 3).exists());
+        let all = tail_in(&dir, "s", 100, &config).unwrap();
+        assert_eq!(
+            messages(all),
+            ["line 2", "line 3", "line 4", "line 5", "line 6", "line 7"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tail_reads_back_through_rotated_files() {
+        let dir = temp_dir();
+        let config = config(64, 3);
+        let mut log = LogFile::open_in(dir.clone(), "s", config.clone()).unwrap();
+        for n in 0..5 {
+            write_line(&mut log, &format!("line {}", n));
+        }
+
+        let last = tail_in(&dir, "s", 3, &config).unwrap();
+        assert_eq!(messages(last), ["line 2", "line 3", "line 4"]);
+        assert!(tail_in(&dir, "s", 0, &config).unwrap().is_empty());
+        assert!(tail_in(&dir, "other", 10, &config).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reopening_appends_to_the_current_file() {
+        let dir = temp_dir();
+        let config = SidecarLogConfig::default();
+        for run in ["first run", "second run"] {
+            let mut log = LogFile::open_in(dir.clone(), "s", config.clone()).unwrap();
+            write_line(&mut log, run);
+        }
+
+        let all = tail_in(&dir, "s", 10, &config).unwrap();
+        assert_eq!(messages(all), ["first run", "second run"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,105 +1,548 @@
-use std::io::{BufRead, BufReader, Write};
-use std::process::{Child, Command, Stdio};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::collections::{HashMap, VecDeque};
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use tauri::{AppHandle, Emitter};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
-use super::protocol::{FromSidecar, ToSidecar};
+use super::launch::SidecarLaunch;
+use super::logs::SidecarLog;
+use super::protocol::{FromSidecar, Request, ToSidecar, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use super::restart::{RestartTracker, SidecarState, SidecarStatus};
+use super::sdk::SdkMessage;
+use super::session::SessionEvent;
+use crate::audio::earcons::{self, Earcon};
+use crate::state::AppState;
+use crate::voice;
 
+/// Stderr lines kept for the `sidecar-status` event after a crash.
+const STDERR_TAIL_LINES: usize = 20;
+/// How long to wait for the process and its output streams to finish once
+/// either side of it has gone away.
+const EXIT_WAIT: Duration = Duration::from_secs(2);
+/// Messages queued for the writer task before `send` reports the sidecar
+/// as stalled rather than waiting on it.
+const STDIN_QUEUE: usize = 64;
+/// How long commands wait for the sidecar to acknowledge a message.
+pub const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the sidecar gets to abort its query and exit after a
+/// `Shutdown` message, before it is sent SIGTERM.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+/// How long the process group gets after SIGTERM before SIGKILL.
+const TERM_GRACE: Duration = Duration::from_secs(2);
+/// Longest a stopped sidecar takes to exit and have its output collected.
+pub const STOP_TIMEOUT: Duration = SHUTDOWN_GRACE
+    .saturating_add(TERM_GRACE)
+    .saturating_add(EXIT_WAIT);
+/// How often `wait_exited` checks whether the sidecar has exited.
+#[cfg(unix)]
+const EXIT_POLL: Duration = Duration::from_millis(50);
+/// How long a new sidecar has to load and answer the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(20);
+/// The mode a new sidecar starts in.
+const DEFAULT_PERMISSION_MODE: &str = "default";
+
+/// A permission request that has been forwarded to the UI but not answered.
+#[derive(Debug, Clone)]
+pub struct PendingPermission {
+    pub request_id: String,
+    pub tool_name: String,
+    pub input: serde_json::Value,
+}
+
+/// A sent message waiting for the sidecar's `Ack` or `Reject`.
+struct PendingAck {
+    sender: oneshot::Sender<Result<(), String>>,
+    /// A rejected `Send` means no turn was started.
+    starts_turn: bool,
+    /// The permission mode a `SetPermissionMode` switches to once accepted.
+    permission_mode: Option<String>,
+}
+
+/// State the stdout reader updates as messages arrive.
+struct Shared {
+    pending_permissions: Mutex<Vec<PendingPermission>>,
+    pending_acks: Mutex<HashMap<u64, PendingAck>>,
+    /// True from a `send` until the turn completes or fails.
+    generating: AtomicBool,
+    /// What the current sidecar announced in its `Hello`.
+    capabilities: Mutex<Option<Vec<String>>>,
+}
+
+/// The sidecar's answer to one message, returned by `SidecarManager::send`.
+/// Dropping it is fine for fire-and-forget callers; a rejection nobody
+/// waits for is reported as a `sidecar-error` event instead.
+pub struct Ack {
+    id: u64,
+    receiver: oneshot::Receiver<Result<(), String>>,
+}
+
+impl Ack {
+    pub async fn wait(self, timeout: Duration) -> Result<()> {
+        match tokio::time::timeout(timeout, self.receiver).await {
+            Ok(Ok(Ok(()))) => Ok(()),
+            Ok(Ok(Err(message))) => bail!(message),
+            Ok(Err(_)) => bail!("Sidecar exited before answering request {}", self.id),
+            Err(_) => bail!(
+                "Sidecar did not answer request {} within {:?}",
+                self.id,
+                timeout
+            ),
+        }
+    }
+}
+
+/// Handles to a running sidecar process. The process itself is owned by its
+/// supervisor task.
+struct Process {
+    id: u64,
+    /// Lines for the writer task, which owns stdin.
+    stdin: mpsc::Sender<String>,
+    /// Tells the supervisor to stop the process; `true` if it was sent a
+    /// `Shutdown` message and should be given time to exit on its own.
+    kill: Option<oneshot::Sender<bool>>,
+    exited: Arc<AtomicBool>,
+    /// Finishes once the process has exited and the exit was handled.
+    supervisor: JoinHandle<()>,
+}
+
+/// One session's sidecar. Background tasks only hold weak references, so
+/// dropping the last `Arc` still shuts the process down. Dropping does not
+/// wait for the process to exit; see `stop` for that.
 pub struct SidecarManager {
-    child: Arc<Mutex<Option<Child>>>,
-    stdin_tx: Arc<Mutex<Option<std::process::ChildStdin>>>,
+    /// Tagged onto every event this sidecar emits.
+    session_id: String,
+    process: Mutex<Option<Process>>,
+    shared: Shared,
+    next_request_id: AtomicU64,
+    /// Working directory the session was opened in, or of the last message
+    /// sent.
+    cwd: Mutex<Option<String>>,
+    /// Last permission mode the sidecar accepted, reapplied after restarts.
+    permission_mode: Mutex<String>,
+    /// How the sidecar was spawned, for restarts.
+    launch: Mutex<Option<SidecarLaunch>>,
+    /// Environment profile applied on top of the launch's environment,
+    /// read again at every start.
+    profile: Mutex<Option<String>>,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    /// The session's log file, opened on first start and kept across
+    /// restarts.
+    log: Arc<Mutex<Option<SidecarLog>>>,
+    restart: Mutex<RestartTracker>,
+    /// Set by `kill` so the exit is not treated as a crash.
+    stopping: AtomicBool,
+    /// Set when the sidecar failed the handshake, so it is not restarted.
+    refused: AtomicBool,
+    /// Id of the latest process, so a late exit of an old one is ignored.
+    process_id: AtomicU64,
 }
 
 impl SidecarManager {
-    pub fn new() -> Self {
-        Self {
-            child: Arc::new(Mutex::new(None)),
-            stdin_tx: Arc::new(Mutex::new(None)),
-        }
+    pub fn new(session_id: &str, cwd: Option<String>, profile: Option<String>) -> Arc<Self> {
+        Arc::new(Self {
+            session_id: session_id.to_string(),
+            process: Mutex::new(None),
+            shared: Shared {
+                pending_permissions: Mutex::new(Vec::new()),
+                pending_acks: Mutex::new(HashMap::new()),
+                generating: AtomicBool::new(false),
+                capabilities: Mutex::new(None),
+            },
+            next_request_id: AtomicU64::new(1),
+            cwd: Mutex::new(cwd),
+            permission_mode: Mutex::new(DEFAULT_PERMISSION_MODE.to_string()),
+            launch: Mutex::new(None),
+            profile: Mutex::new(profile),
+            stderr_tail: Arc::new(Mutex::new(VecDeque::new())),
+            log: Arc::new(Mutex::new(None)),
+            restart: Mutex::new(RestartTracker::new()),
+            stopping: AtomicBool::new(false),
+            refused: AtomicBool::new(false),
+            process_id: AtomicU64::new(0),
+        })
     }
 
-    pub fn spawn(&self, sidecar_path: &str, app_handle: AppHandle) -> Result<()> {
-        info!("Spawning sidecar: {}", sidecar_path);
+    /// Start the sidecar, replacing the launch used for restarts. An
+    /// explicit spawn starts the crash count over, so it also recovers a
+    /// sidecar that was given up on.
+    pub fn spawn(self: &Arc<Self>, launch: SidecarLaunch, app_handle: AppHandle) -> Result<()> {
+        *self.launch.lock().unwrap() = Some(launch);
+        *self.restart.lock().unwrap() = RestartTracker::new();
+        self.stopping.store(false, Ordering::SeqCst);
+        self.refused.store(false, Ordering::SeqCst);
+        self.start(app_handle)
+    }
 
-        let mut child = Command::new(sidecar_path)
+    fn start(self: &Arc<Self>, app_handle: AppHandle) -> Result<()> {
+        let launch = self
+            .launch
+            .lock()
+            .unwrap()
+            .clone()
+            .context("Sidecar launch not set")?;
+        let profile = self.profile();
+        let profile_env = match &profile {
+            Some(name) => app_handle
+                .state::<AppState>()
+                .profiles
+                .lock()
+                .unwrap()
+                .env(name)?,
+            None => HashMap::new(),
+        };
+        info!(
+            "Spawning sidecar for session {} with profile {:?}: {}",
+            self.session_id, profile, launch
+        );
+
+        // Callers are plain threads; the process and its tasks belong to
+        // Tauri's runtime
+        let runtime = tauri::async_runtime::handle();
+        let _guard = runtime.inner().enter();
+
+        let mut command = Command::new(&launch.program);
+        command
+            .args(&launch.args)
+            .envs(&launch.env)
+            .envs(&profile_env)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(working_dir) = &launch.working_dir {
+            command.current_dir(working_dir);
+        }
+        // Its own process group, so shutdown reaches the agent's children
+        #[cfg(unix)]
+        command.process_group(0);
+        self.open_log(&app_handle);
+        let mut child = command
             .spawn()
-            .context("Failed to spawn sidecar process")?;
+            .context("Failed to spawn sidecar process")
+            .inspect_err(|e| self.log_line(&format!("{:#}", e)))?;
+        self.log_line(&format!(
+            "Sidecar started: {} (pid {})",
+            launch,
+            child.id().unwrap_or_default()
+        ));
 
         let stdin = child.stdin.take().context("Failed to get sidecar stdin")?;
-        let stdout = child.stdout.take().context("Failed to get sidecar stdout")?;
-        let stderr = child.stderr.take().context("Failed to get sidecar stderr")?;
-
-        *self.stdin_tx.lock().unwrap() = Some(stdin);
-        *self.child.lock().unwrap() = Some(child);
-
-        // Read stdout in a separate thread
-        let app_handle_clone = app_handle.clone();
-        thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        match serde_json::from_str::<FromSidecar>(&line) {
-                            Ok(msg) => {
-                                Self::handle_message(&app_handle_clone, msg);
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse sidecar message: {} — line: {}", e, line);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("Error reading sidecar stdout: {}", e);
-                        break;
-                    }
-                }
-            }
-            info!("Sidecar stdout reader exited");
-            let _ = app_handle_clone.emit("sidecar-exited", ());
-        });
+        let stdout = child
+            .stdout
+            .take()
+            .context("Failed to get sidecar stdout")?;
+        let stderr = child
+            .stderr
+            .take()
+            .context("Failed to get sidecar stderr")?;
 
-        // Read stderr for logging
-        thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        if !line.trim().is_empty() {
-                            info!("[sidecar stderr] {}", line);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Error reading sidecar stderr: {}", e);
-                        break;
+        let process_id = self.process_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.stderr_tail.lock().unwrap().clear();
+        *self.shared.capabilities.lock().unwrap() = None;
+
+        let (stdin_tx, stdin_rx) = mpsc::channel(STDIN_QUEUE);
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let exited = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn(write_stdin(stdin, stdin_rx));
+        let stdout_task = tokio::spawn(read_stdout(
+            app_handle.clone(),
+            stdout,
+            Arc::downgrade(self),
+            process_id,
+        ));
+        let stderr_task = tokio::spawn(read_stderr(
+            stderr,
+            self.stderr_tail.clone(),
+            self.log.clone(),
+        ));
+        let supervisor = tokio::spawn(supervise(
+            app_handle.clone(),
+            Arc::downgrade(self),
+            process_id,
+            child,
+            stdout_task,
+            stderr_task,
+            kill_rx,
+            exited.clone(),
+        ));
+
+        *self.process.lock().unwrap() = Some(Process {
+            id: process_id,
+            stdin: stdin_tx,
+            kill: Some(kill_tx),
+            exited,
+            supervisor,
+        });
+        // The sidecar may also refuse us, if this app is too old for it
+        let hello = self.send(&ToSidecar::Hello {
+            protocol_version: PROTOCOL_VERSION,
+        })?;
+        let hello_app_handle = app_handle.clone();
+        let sidecar = Arc::downgrade(self);
+        tokio::spawn(async move {
+            match hello.wait(HANDSHAKE_TIMEOUT).await {
+                Ok(()) => {
+                    if let Some(sidecar) = sidecar.upgrade() {
+                        sidecar.restore_permission_mode();
                     }
                 }
+                Err(e) => refuse(
+                    hello_app_handle,
+                    sidecar,
+                    process_id,
+                    format!(
+                        "Sidecar did not accept protocol version {}: {}",
+                        PROTOCOL_VERSION, e
+                    ),
+                ),
             }
         });
 
+        let attempt = {
+            let mut restart = self.restart.lock().unwrap();
+            restart.started();
+            restart.attempt()
+        };
+        self.emit(
+            &app_handle,
+            "sidecar-status",
+            SidecarStatus::running(attempt),
+        );
+
         Ok(())
     }
 
-    fn handle_message(app_handle: &AppHandle, msg: FromSidecar) {
+    fn open_log(&self, app_handle: &AppHandle) {
+        let mut log = self.log.lock().unwrap();
+        if log.is_some() {
+            return;
+        }
+        let state = app_handle.state::<AppState>();
+        let config = state.settings.lock().unwrap().sidecar_logs.clone();
+        match SidecarLog::open(&self.session_id, config) {
+            Ok(opened) => *log = Some(opened),
+            Err(e) => warn!("Sidecar output will not be logged to a file: {:#}", e),
+        }
+    }
+
+    fn log_line(&self, line: &str) {
+        if let Some(log) = self.log.lock().unwrap().as_ref() {
+            log.write_line(line);
+        }
+    }
+
+    /// A new process starts in the default mode; put the session back in
+    /// the one it was using.
+    fn restore_permission_mode(&self) {
+        let mode = self.permission_mode();
+        if mode == DEFAULT_PERMISSION_MODE || !self.supports("permissionModes") {
+            return;
+        }
+        info!(
+            "Restoring permission mode {} for session {}",
+            mode, self.session_id
+        );
+        if let Err(e) = self.send(&ToSidecar::SetPermissionMode { mode }) {
+            warn!("Failed to restore permission mode: {}", e);
+        }
+    }
+
+    /// Emit an event tagged with this sidecar's session.
+    pub fn emit<S: Serialize + Clone>(&self, app_handle: &AppHandle, event: &str, payload: S) {
+        let _ = app_handle.emit(
+            event,
+            SessionEvent {
+                session_id: &self.session_id,
+                payload,
+            },
+        );
+    }
+
+    /// Called by the supervisor once the process has exited and its output
+    /// has been read. Reports the exit, fails outstanding permission
+    /// requests and schedules a restart unless the exit was requested or
+    /// the sidecar is crash-looping.
+    fn handle_exit(
+        self: &Arc<Self>,
+        app_handle: &AppHandle,
+        process_id: u64,
+        status: Option<ExitStatus>,
+    ) {
+        if process_id != self.process_id.load(Ordering::SeqCst) {
+            return;
+        }
+
+        {
+            let mut process = self.process.lock().unwrap();
+            if process.as_ref().is_some_and(|p| p.id == process_id) {
+                *process = None;
+            }
+        }
+        self.shared.generating.store(false, Ordering::SeqCst);
+        self.emit(app_handle, "sidecar-exited", ());
+        self.fail_pending_permissions(app_handle);
+        // Dropping the senders fails every `Ack::wait`
+        self.shared.pending_acks.lock().unwrap().clear();
+
+        let stopping = self.stopping.load(Ordering::SeqCst);
+        let refused = self.refused.load(Ordering::SeqCst);
+        let delay = if stopping || refused {
+            None
+        } else {
+            self.restart.lock().unwrap().crashed()
+        };
+        let state = match (stopping, refused, delay) {
+            (true, _, _) => SidecarState::Stopped,
+            (false, true, _) => SidecarState::Failed,
+            (false, false, Some(_)) => SidecarState::Restarting,
+            (false, false, None) => SidecarState::Failed,
+        };
+
+        let report = SidecarStatus {
+            state,
+            exit_code: status.and_then(|s| s.code()),
+            signal: status.and_then(exit_signal),
+            stderr: self.stderr_tail.lock().unwrap().iter().cloned().collect(),
+            restart_in_ms: delay.map(|d| d.as_millis() as u64),
+            attempt: self.restart.lock().unwrap().attempt(),
+        };
+        match state {
+            SidecarState::Stopped => info!("Sidecar for session {} stopped", self.session_id),
+            _ => error!(
+                "Sidecar for session {} exited unexpectedly: {:?}",
+                self.session_id, report
+            ),
+        }
+        self.log_line(&format!(
+            "Sidecar exited ({:?}): code {:?}, signal {:?}",
+            report.state, report.exit_code, report.signal
+        ));
+        self.emit(app_handle, "sidecar-status", &report);
+
+        match delay {
+            Some(delay) => self.schedule_restart(app_handle.clone(), delay, process_id),
+            // A refused sidecar has reported why already
+            None if state == SidecarState::Failed && !refused => {
+                let message = "Sidecar keeps crashing; not restarting it again".to_string();
+                self.emit(app_handle, "sidecar-error", &message);
+                earcons::cue(app_handle, Earcon::Error);
+            }
+            None => {}
+        }
+    }
+
+    /// Stop a sidecar that speaks an incompatible protocol. It is not
+    /// restarted, since the same binary would fail the same way.
+    fn refuse(&self, app_handle: &AppHandle, process_id: u64, message: String) {
+        let mut process = self.process.lock().unwrap();
+        // Exited already, or a newer process has taken over
+        let Some(process) = process.as_mut().filter(|p| p.id == process_id) else {
+            return;
+        };
+        if self.refused.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        error!("{}", message);
+        self.emit(app_handle, "sidecar-incompatible", &message);
+        earcons::cue(app_handle, Earcon::Error);
+        if let Some(kill) = process.kill.take() {
+            let _ = kill.send(false);
+        }
+    }
+
+    /// The sidecar that asked for these permissions is gone, so nobody is
+    /// waiting for the answers any more.
+    fn fail_pending_permissions(&self, app_handle: &AppHandle) {
+        let pending = std::mem::take(&mut *self.shared.pending_permissions.lock().unwrap());
+        for request in pending {
+            warn!(
+                "Dropping permission request {} after sidecar exit",
+                request.request_id
+            );
+            self.emit(
+                app_handle,
+                "permission-cancelled",
+                serde_json::json!({
+                    "requestId": request.request_id,
+                    "reason": "Sidecar exited",
+                }),
+            );
+        }
+    }
+
+    fn schedule_restart(self: &Arc<Self>, app_handle: AppHandle, delay: Duration, process_id: u64) {
+        info!(
+            "Restarting sidecar for session {} in {:?}",
+            self.session_id, delay
+        );
+        let sidecar = Arc::downgrade(self);
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                // The session was closed in the meantime
+                let Some(sidecar) = sidecar.upgrade() else {
+                    return;
+                };
+                // Killed or restarted by someone else in the meantime
+                if sidecar.stopping.load(Ordering::SeqCst)
+                    || sidecar.process_id.load(Ordering::SeqCst) != process_id
+                {
+                    return;
+                }
+                if let Err(e) = sidecar.start(app_handle.clone()) {
+                    error!("Failed to restart sidecar: {}", e);
+                    sidecar.handle_exit(&app_handle, process_id, None);
+                }
+            })
+            .await;
+        });
+    }
+
+    fn handle_message(self: &Arc<Self>, app_handle: &AppHandle, msg: FromSidecar) {
+        let shared = &self.shared;
         match &msg {
+            FromSidecar::Hello { .. } => {
+                warn!("Ignoring repeated hello from sidecar");
+            }
             FromSidecar::SdkMessage { message } => {
-                let _ = app_handle.emit("sdk-message", message);
+                log_sdk_message(message);
+                self.emit(app_handle, "sdk-message", message);
+                // Background sessions would talk over the one in front
+                let state = app_handle.state::<AppState>();
+                if state.sessions.lock().unwrap().active_id() == self.session_id {
+                    state.speaker.lock().unwrap().speak_message(message);
+                }
             }
             FromSidecar::PermissionRequest {
                 request_id,
                 tool_name,
                 input,
             } => {
-                let _ = app_handle.emit(
+                let request = PendingPermission {
+                    request_id: request_id.clone(),
+                    tool_name: tool_name.clone(),
+                    input: input.clone(),
+                };
+                shared
+                    .pending_permissions
+                    .lock()
+                    .unwrap()
+                    .push(request.clone());
+                voice::permission::spawn_prompt(app_handle.clone(), Arc::downgrade(self), request);
+                earcons::cue(app_handle, Earcon::PermissionRequest);
+                self.emit(
+                    app_handle,
                     "permission-request",
                     serde_json::json!({
                         "requestId": request_id,
@@ -109,59 +552,554 @@ impl SidecarManager {
                 );
             }
             FromSidecar::SessionReady { session_id } => {
-                let _ = app_handle.emit("session-ready", session_id);
+                self.emit(app_handle, "session-ready", session_id);
             }
             FromSidecar::StreamingText { text } => {
-                let _ = app_handle.emit("streaming-text", text);
+                self.emit(app_handle, "streaming-text", text);
             }
             FromSidecar::TurnComplete { messages } => {
-                let _ = app_handle.emit("turn-complete", messages);
+                shared.generating.store(false, Ordering::SeqCst);
+                self.emit(app_handle, "turn-complete", messages);
+                earcons::cue(app_handle, Earcon::TurnComplete);
             }
             FromSidecar::Error { message } => {
-                error!("Sidecar error: {}", message);
-                let _ = app_handle.emit("sidecar-error", message);
+                shared.generating.store(false, Ordering::SeqCst);
+                error!("Sidecar error in session {}: {}", self.session_id, message);
+                self.emit(app_handle, "sidecar-error", message);
+                earcons::cue(app_handle, Earcon::Error);
+            }
+            FromSidecar::Ack { id } => {
+                if let Some(pending) = shared.pending_acks.lock().unwrap().remove(id) {
+                    if let Some(mode) = pending.permission_mode {
+                        *self.permission_mode.lock().unwrap() = mode;
+                    }
+                    let _ = pending.sender.send(Ok(()));
+                }
+            }
+            FromSidecar::Reject { id, message } => {
+                warn!("Sidecar rejected request {}: {}", id, message);
+                let Some(pending) = shared.pending_acks.lock().unwrap().remove(id) else {
+                    return;
+                };
+                if pending.starts_turn {
+                    shared.generating.store(false, Ordering::SeqCst);
+                }
+                if pending.sender.send(Err(message.clone())).is_err() {
+                    self.emit(app_handle, "sidecar-error", message);
+                    earcons::cue(app_handle, Earcon::Error);
+                }
             }
         }
     }
 
-    pub fn send(&self, msg: &ToSidecar) -> Result<()> {
-        let mut stdin_guard = self.stdin_tx.lock().unwrap();
-        let stdin = stdin_guard
-            .as_mut()
-            .context("Sidecar stdin not available")?;
+    /// Queue a message for the sidecar. Never waits on the process: a
+    /// sidecar that has stopped reading its input is reported as an error.
+    /// The returned `Ack` resolves once the sidecar accepts or rejects it.
+    pub fn send(&self, msg: &ToSidecar) -> Result<Ack> {
+        // Before the handshake nothing is known; the sidecar rejects what
+        // it does not understand
+        if let (Some(capability), Some(capabilities)) = (
+            msg.required_capability(),
+            self.shared.capabilities.lock().unwrap().as_ref(),
+        ) {
+            if !capabilities.iter().any(|c| c == capability) {
+                bail!(
+                    "Sidecar does not support '{}'; update it to use this feature",
+                    capability
+                );
+            }
+        }
 
-        let json = serde_json::to_string(msg)?;
-        writeln!(stdin, "{}", json)?;
-        stdin.flush()?;
-        Ok(())
+        // Messages without a directory run where the session was opened
+        let with_cwd;
+        let msg = match msg {
+            ToSidecar::Send { text, cwd: None } => {
+                with_cwd = ToSidecar::Send {
+                    text: text.clone(),
+                    cwd: self.cwd(),
+                };
+                &with_cwd
+            }
+            _ => msg,
+        };
+
+        let id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        let json = serde_json::to_string(&Request { id, message: msg })?;
+
+        // Registered first so an immediate answer always finds its waiter
+        let (sender, receiver) = oneshot::channel();
+        self.shared.pending_acks.lock().unwrap().insert(
+            id,
+            PendingAck {
+                sender,
+                starts_turn: matches!(msg, ToSidecar::Send { .. }),
+                permission_mode: match msg {
+                    ToSidecar::SetPermissionMode { mode } => Some(mode.clone()),
+                    _ => None,
+                },
+            },
+        );
+        let queued = match self.process.lock().unwrap().as_ref() {
+            Some(process) => process.stdin.try_send(json).map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    anyhow!("Sidecar is not reading its input")
+                }
+                mpsc::error::TrySendError::Closed(_) => anyhow!("Sidecar stdin not available"),
+            }),
+            None => Err(anyhow!("Sidecar stdin not available")),
+        };
+        if let Err(e) = queued {
+            self.shared.pending_acks.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        match msg {
+            ToSidecar::Send { cwd, .. } => {
+                self.shared.generating.store(true, Ordering::SeqCst);
+                if cwd.is_some() {
+                    *self.cwd.lock().unwrap() = cwd.clone();
+                }
+            }
+            ToSidecar::RespondPermission { request_id, .. } => self
+                .shared
+                .pending_permissions
+                .lock()
+                .unwrap()
+                .retain(|p| p.request_id != *request_id),
+            _ => {}
+        }
+        Ok(Ack { id, receiver })
+    }
+
+    /// The oldest permission request still waiting for an answer.
+    pub fn oldest_pending_permission(&self) -> Option<PendingPermission> {
+        self.shared
+            .pending_permissions
+            .lock()
+            .unwrap()
+            .first()
+            .cloned()
+    }
+
+    /// Whether the agent is working on a turn.
+    pub fn is_generating(&self) -> bool {
+        self.shared.generating.load(Ordering::SeqCst)
+    }
+
+    pub fn is_permission_pending(&self, request_id: &str) -> bool {
+        self.shared
+            .pending_permissions
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|p| p.request_id == request_id)
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn cwd(&self) -> Option<String> {
+        self.cwd.lock().unwrap().clone()
+    }
+
+    pub fn set_cwd(&self, cwd: String) {
+        *self.cwd.lock().unwrap() = Some(cwd);
+    }
+
+    pub fn profile(&self) -> Option<String> {
+        self.profile.lock().unwrap().clone()
+    }
+
+    /// Use another environment profile from the next start on.
+    pub fn set_profile(&self, profile: Option<String>) {
+        *self.profile.lock().unwrap() = profile;
+    }
+
+    /// Replace the running sidecar with a new one, e.g. to apply another
+    /// environment profile. Refused mid-turn, since the turn would be lost.
+    pub fn restart(self: &Arc<Self>, app_handle: AppHandle) -> Result<()> {
+        if self.is_generating() {
+            bail!("Wait for the current turn to finish before restarting the sidecar");
+        }
+        let launch = self
+            .launch
+            .lock()
+            .unwrap()
+            .clone()
+            .context("Sidecar was never started")?;
+        self.kill()?;
+        self.spawn(launch, app_handle)
+    }
+
+    pub fn permission_mode(&self) -> String {
+        self.permission_mode.lock().unwrap().clone()
     }
 
     pub fn is_running(&self) -> bool {
-        let mut child_guard = self.child.lock().unwrap();
-        if let Some(child) = child_guard.as_mut() {
-            match child.try_wait() {
-                Ok(None) => true,  // Still running
-                Ok(Some(_)) => false,  // Exited
-                Err(_) => false,
+        self.process
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|p| !p.exited.load(Ordering::SeqCst))
+    }
+
+    /// Stop the sidecar: ask it to shut down if it supports that, then
+    /// escalate to SIGTERM and SIGKILL. Returns without waiting; the
+    /// supervisor runs the sequence and logs how it ended.
+    pub fn kill(&self) -> Result<()> {
+        self.stop();
+        Ok(())
+    }
+
+    /// Like `kill`, but returns the supervisor so the caller can wait for
+    /// the process to exit, which takes up to `STOP_TIMEOUT`. Only await it
+    /// where blocking is fine, such as at app exit.
+    pub fn stop(&self) -> Option<JoinHandle<()>> {
+        self.stopping.store(true, Ordering::SeqCst);
+        let graceful = self.supports("shutdown") && self.send(&ToSidecar::Shutdown).is_ok();
+        let mut process = self.process.lock().unwrap().take()?;
+        if let Some(kill) = process.kill.take() {
+            // Already gone if the supervisor has dropped the receiver
+            let _ = kill.send(graceful);
+        }
+        Some(process.supervisor)
+    }
+
+    /// Whether the current sidecar announced `capability` in its hello.
+    fn supports(&self, capability: &str) -> bool {
+        self.shared
+            .capabilities
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|c| c.iter().any(|c| c == capability))
+    }
+}
+
+async fn write_stdin(mut stdin: ChildStdin, mut lines: mpsc::Receiver<String>) {
+    while let Some(line) = lines.recv().await {
+        let result = async {
+            stdin.write_all(line.as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+            stdin.flush().await
+        }
+        .await;
+        if let Err(e) = result {
+            error!("Error writing to sidecar stdin: {}", e);
+            break;
+        }
+    }
+}
+
+async fn read_stdout(
+    app_handle: AppHandle,
+    stdout: ChildStdout,
+    sidecar: Weak<SidecarManager>,
+    process_id: u64,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    let mut greeted = false;
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let msg = serde_json::from_str::<FromSidecar>(&line);
+                // The session was closed; the process is being stopped
+                let Some(sidecar) = sidecar.upgrade() else {
+                    break;
+                };
+                if !greeted {
+                    // Anything but a hello first means a sidecar from
+                    // before the protocol was versioned
+                    let result = match &msg {
+                        Ok(FromSidecar::Hello {
+                            protocol_version,
+                            capabilities,
+                        }) => accept_hello(&sidecar.shared, *protocol_version, capabilities),
+                        _ => Err(format!(
+                            "Sidecar does not announce a protocol version; rebuild it for protocol {}",
+                            PROTOCOL_VERSION
+                        )),
+                    };
+                    if let Err(message) = result {
+                        refuse(
+                            app_handle.clone(),
+                            Arc::downgrade(&sidecar),
+                            process_id,
+                            message,
+                        );
+                        break;
+                    }
+                    greeted = true;
+                    continue;
+                }
+                match msg {
+                    Ok(msg) => {
+                        sidecar.handle_message(&app_handle, msg);
+                    }
+                    Err(e) => {
+                        warn!("Failed to parse sidecar message: {} — line: {}", e, line);
+                    }
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                error!("Error reading sidecar stdout: {}", e);
+                break;
             }
-        } else {
-            false
         }
     }
+    info!("Sidecar stdout reader exited");
+}
 
-    pub fn kill(&self) -> Result<()> {
-        let mut child_guard = self.child.lock().unwrap();
-        if let Some(child) = child_guard.as_mut() {
-            child.kill()?;
+fn accept_hello(
+    shared: &Shared,
+    protocol_version: u32,
+    capabilities: &[String],
+) -> Result<(), String> {
+    if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version) {
+        return Err(format!(
+            "Sidecar speaks protocol version {}, but this app supports {} to {}; install a matching sidecar",
+            protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        ));
+    }
+    info!(
+        "Sidecar protocol version {}, capabilities: {}",
+        protocol_version,
+        capabilities.join(", ")
+    );
+    *shared.capabilities.lock().unwrap() = Some(capabilities.to_vec());
+    Ok(())
+}
+
+/// `SidecarManager::refuse` from a task, which must not block on the
+/// error cue.
+fn refuse(app_handle: AppHandle, sidecar: Weak<SidecarManager>, process_id: u64, message: String) {
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(sidecar) = sidecar.upgrade() {
+            sidecar.refuse(&app_handle, process_id, message);
         }
-        *child_guard = None;
-        *self.stdin_tx.lock().unwrap() = None;
-        Ok(())
+    });
+}
+
+fn log_sdk_message(message: &SdkMessage) {
+    match message {
+        SdkMessage::Assistant(message) => {
+            for tool in message.tool_uses() {
+                info!("Agent calls {} ({})", tool.name, tool.id);
+            }
+        }
+        SdkMessage::User(message) => {
+            for result in message.tool_results().filter(|r| r.is_error == Some(true)) {
+                warn!("Tool call {} failed", result.tool_use_id);
+            }
+        }
+        SdkMessage::Result(result) if result.is_error => {
+            warn!("Query ended with {}", result.subtype);
+        }
+        SdkMessage::Unknown(message) => {
+            info!("Unrecognized SDK message: {}", message);
+        }
+        _ => {}
+    }
+}
+
+/// Log stderr to tracing and the session's log file, keeping the tail for
+/// crash reports.
+async fn read_stderr(
+    stderr: ChildStderr,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    log: Arc<Mutex<Option<SidecarLog>>>,
+) {
+    let mut lines = BufReader::new(stderr).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                info!("[sidecar stderr] {}", line);
+                if let Some(log) = log.lock().unwrap().as_ref() {
+                    log.write_line(&line);
+                }
+                let mut tail = stderr_tail.lock().unwrap();
+                if tail.len() == STDERR_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+            Ok(None) => break,
+            Err(e) => {
+                error!("Error reading sidecar stderr: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// Own the process until it exits, then hand over to `handle_exit`.
+#[allow(clippy::too_many_arguments)]
+async fn supervise(
+    app_handle: AppHandle,
+    sidecar: Weak<SidecarManager>,
+    process_id: u64,
+    mut child: Child,
+    mut stdout_task: JoinHandle<()>,
+    mut stderr_task: JoinHandle<()>,
+    kill: oneshot::Receiver<bool>,
+    exited: Arc<AtomicBool>,
+) {
+    let status = tokio::select! {
+        status = child.wait() => status,
+        // Requested, or the manager is gone
+        graceful = kill => shut_down(&mut child, graceful.unwrap_or(false)).await,
+        _ = &mut stdout_task => {
+            match tokio::time::timeout(EXIT_WAIT, child.wait()).await {
+                Ok(status) => status,
+                Err(_) => {
+                    // Closed stdout but kept running; it is no use to us
+                    warn!("Sidecar closed stdout but did not exit; stopping it");
+                    shut_down(&mut child, false).await
+                }
+            }
+        }
+    };
+    exited.store(true, Ordering::SeqCst);
+    let status = status
+        .map_err(|e| error!("Failed to get sidecar exit status: {}", e))
+        .ok();
+
+    // Output still buffered in the pipes belongs to this process; a
+    // grandchild holding them open must not keep us waiting
+    for task in [&mut stdout_task, &mut stderr_task] {
+        if !task.is_finished() && tokio::time::timeout(EXIT_WAIT, &mut *task).await.is_err() {
+            task.abort();
+        }
+    }
+
+    // Nothing to report for a closed session
+    let Some(sidecar) = sidecar.upgrade() else {
+        return;
+    };
+    let _ = tauri::async_runtime::spawn_blocking(move || {
+        sidecar.handle_exit(&app_handle, process_id, status);
+    })
+    .await;
+}
+
+#[cfg(unix)]
+fn exit_signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Wait out the grace period after a `Shutdown` message, then SIGTERM and
+/// finally SIGKILL the sidecar's process group, logging which step worked.
+async fn shut_down(child: &mut Child, graceful: bool) -> std::io::Result<ExitStatus> {
+    let pid = child.id();
+
+    if graceful {
+        if wait_exited(child, SHUTDOWN_GRACE).await {
+            info!("Sidecar shut down cleanly");
+            // Anything the agent started and left running
+            if let Some(pid) = pid {
+                signal_group(pid, Signal::Term);
+            }
+            return child.wait().await;
+        }
+        warn!(
+            "Sidecar did not exit within {:?} of the shutdown request",
+            SHUTDOWN_GRACE
+        );
     }
+
+    if let Some(pid) = pid {
+        if signal_group(pid, Signal::Term) {
+            if wait_exited(child, TERM_GRACE).await {
+                info!("Sidecar exited after SIGTERM");
+                signal_group(pid, Signal::Kill);
+                return child.wait().await;
+            }
+            warn!("Sidecar did not exit within {:?} of SIGTERM", TERM_GRACE);
+        }
+        signal_group(pid, Signal::Kill);
+    }
+    child.kill().await?;
+    warn!("Sidecar killed");
+    child.wait().await
+}
+
+/// Wait up to `timeout` for the sidecar to exit, without reaping it. Until
+/// it is reaped its pid, which is also its process group id, cannot be
+/// reused, so the group can still be signalled safely.
+#[cfg(unix)]
+async fn wait_exited(child: &mut Child, timeout: Duration) -> bool {
+    let Some(pid) = child.id() else {
+        return true;
+    };
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        // SAFETY: waitid(2) only writes to `info`, which is zeroed and ours
+        let exited = unsafe {
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            let flags = libc::WEXITED | libc::WNOHANG | libc::WNOWAIT;
+            match libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) {
+                // A running child leaves `info` zeroed
+                0 => info.si_pid() != 0,
+                _ => std::io::Error::last_os_error().raw_os_error() == Some(libc::ECHILD),
+            }
+        };
+        if exited {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(EXIT_POLL).await;
+    }
+}
+
+/// No process groups to protect, so waiting may reap the sidecar.
+#[cfg(not(unix))]
+async fn wait_exited(child: &mut Child, timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, child.wait()).await.is_ok()
+}
+
+enum Signal {
+    Term,
+    Kill,
+}
+
+/// Send a signal to the process group led by `pid`. Returns whether any
+/// process received it.
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: Signal) -> bool {
+    let signal = match signal {
+        Signal::Term => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    // SAFETY: kill(2) takes plain integers and has no memory effects
+    unsafe { libc::kill(-(pid as libc::pid_t), signal) == 0 }
+}
+
+/// No process groups: `shut_down` falls through to killing the child.
+#[cfg(not(unix))]
+fn signal_group(_pid: u32, _signal: Signal) -> bool {
+    false
 }
 
 impl Drop for SidecarManager {
     fn drop(&mut self) {
+        // Runs on whichever thread lets go last, possibly one holding the
+        // sessions lock, so only start the shutdown here
         let _ = self.kill();
     }
 }
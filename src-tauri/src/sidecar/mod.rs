@@ -1,2 +1,8 @@
+pub mod launch;
+pub mod logs;
 pub mod manager;
+pub mod profiles;
 pub mod protocol;
+pub mod restart;
+pub mod sdk;
+pub mod session;
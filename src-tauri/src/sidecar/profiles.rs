@@ -0,0 +1,405 @@
+//! Named environment profiles for the sidecar, e.g. one API key and proxy
+//! per client project. Plain values live in `~/.voxcode/profiles.json`,
+//! secret values in `~/.voxcode/secrets.json`, which only the user can read.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::settings::voxcode_dir;
+
+/// Variables of one profile, keyed by name.
+type Vars = BTreeMap<String, String>;
+
+/// A profile as the UI sees it: secret names, but never their values.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvProfile {
+    pub name: String,
+    pub env: Vars,
+    pub secrets: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ProfilesFile {
+    /// Used by sessions opened without a profile, including the default one.
+    default_profile: Option<String>,
+    profiles: BTreeMap<String, Vars>,
+}
+
+/// Secret values, keyed by profile name.
+type Secrets = BTreeMap<String, Vars>;
+
+pub struct ProfileStore {
+    /// Holds `profiles.json` and `secrets.json`.
+    dir: PathBuf,
+    file: ProfilesFile,
+    secrets: Secrets,
+    /// Why a file that exists could not be loaded. Saving would replace it,
+    /// and any credentials in it, with what was loaded, so it is refused
+    /// until the file is fixed or removed.
+    load_error: Option<String>,
+}
+
+impl ProfileStore {
+    /// Load both files, starting empty if either is missing or unreadable.
+    pub fn load() -> Self {
+        Self::load_from(voxcode_dir())
+    }
+
+    fn load_from(dir: PathBuf) -> Self {
+        let mut store = Self {
+            dir,
+            file: ProfilesFile::default(),
+            secrets: Secrets::new(),
+            load_error: None,
+        };
+        let secrets_path = store.secrets_path();
+        restrict_permissions(&secrets_path);
+        store.file = load_json(&store.path(), &mut store.load_error);
+        store.secrets = load_json(&secrets_path, &mut store.load_error);
+        store
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("profiles.json")
+    }
+
+    pub fn secrets_path(&self) -> PathBuf {
+        self.dir.join("secrets.json")
+    }
+
+    pub fn list(&self) -> Vec<EnvProfile> {
+        self.file
+            .profiles
+            .iter()
+            .map(|(name, env)| EnvProfile {
+                name: name.clone(),
+                env: env.clone(),
+                secrets: self
+                    .secrets
+                    .get(name)
+                    .map(|secrets| secrets.keys().cloned().collect())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.file.profiles.contains_key(name)
+    }
+
+    pub fn default_profile(&self) -> Option<String> {
+        self.file.default_profile.clone()
+    }
+
+    pub fn set_default_profile(&mut self, name: Option<String>) -> Result<()> {
+        self.check_loaded()?;
+        if let Some(name) = &name {
+            self.check_exists(name)?;
+        }
+        let mut file = self.file.clone();
+        file.default_profile = name;
+        self.commit(file, self.secrets.clone())
+    }
+
+    /// Create or replace a profile. A secret given as `None` keeps its
+    /// stored value, since the UI never sees it; secrets left out are
+    /// removed.
+    pub fn save_profile(
+        &mut self,
+        name: &str,
+        env: Vars,
+        secrets: BTreeMap<String, Option<String>>,
+    ) -> Result<()> {
+        self.check_loaded()?;
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Profile name must not be empty");
+        }
+        for key in env.keys().chain(secrets.keys()) {
+            check_var_name(key)?;
+        }
+        if let Some(key) = env.keys().find(|key| secrets.contains_key(*key)) {
+            bail!("{} cannot be both a plain and a secret variable", key);
+        }
+
+        let stored = self.secrets.get(name);
+        let mut kept = Vars::new();
+        for (key, value) in secrets {
+            let value = match value {
+                Some(value) => value,
+                None => stored
+                    .and_then(|stored| stored.get(&key))
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No value stored for secret {}", key))?,
+            };
+            kept.insert(key, value);
+        }
+        let mut all_secrets = self.secrets.clone();
+        if kept.is_empty() {
+            all_secrets.remove(name);
+        } else {
+            all_secrets.insert(name.to_string(), kept);
+        }
+        let mut file = self.file.clone();
+        file.profiles.insert(name.to_string(), env);
+        self.commit(file, all_secrets)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        self.check_loaded()?;
+        self.check_exists(name)?;
+        let mut file = self.file.clone();
+        file.profiles.remove(name);
+        if file.default_profile.as_deref() == Some(name) {
+            file.default_profile = None;
+        }
+        let mut secrets = self.secrets.clone();
+        secrets.remove(name);
+        self.commit(file, secrets)
+    }
+
+    /// Everything a sidecar started with this profile gets on top of the
+    /// app's environment.
+    pub fn env(&self, name: &str) -> Result<HashMap<String, String>> {
+        // Starting without the profile's secrets would fail less clearly
+        self.check_loaded()?;
+        let mut env: HashMap<String, String> = self
+            .file
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("No environment profile '{}'", name))?
+            .clone()
+            .into_iter()
+            .collect();
+        if let Some(secrets) = self.secrets.get(name) {
+            env.extend(secrets.clone());
+        }
+        Ok(env)
+    }
+
+    fn check_exists(&self, name: &str) -> Result<()> {
+        if !self.contains(name) {
+            bail!("No environment profile '{}'", name);
+        }
+        Ok(())
+    }
+
+    fn check_loaded(&self) -> Result<()> {
+        match &self.load_error {
+            Some(error) => bail!("{}; fix or remove it to use environment profiles", error),
+            None => Ok(()),
+        }
+    }
+
+    /// Save `file` and `secrets`, and only then use them, so a failed save
+    /// leaves the store as it was.
+    fn commit(&mut self, file: ProfilesFile, secrets: Secrets) -> Result<()> {
+        self.check_loaded()?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let json = serde_json::to_string_pretty(&file)?;
+        fs::write(self.path(), json)
+            .with_context(|| format!("Failed to write {}", self.path().display()))?;
+        write_private(
+            &self.secrets_path(),
+            &serde_json::to_string_pretty(&secrets)?,
+        )?;
+        self.file = file;
+        self.secrets = secrets;
+        Ok(())
+    }
+}
+
+/// Read `path`, or the default if it does not exist. A file that exists
+/// but cannot be read is recorded in `error` rather than treated as empty.
+fn load_json<T: serde::de::DeserializeOwned + Default>(
+    path: &Path,
+    error: &mut Option<String>,
+) -> T {
+    if !path.exists() {
+        return T::default();
+    }
+    match fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|s| serde_json::from_str(&s).map_err(anyhow::Error::from))
+    {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            error.get_or_insert_with(|| format!("Failed to read {}: {}", path.display(), e));
+            T::default()
+        }
+    }
+}
+
+/// Variable names the OS accepts, so a bad one fails here rather than when
+/// the sidecar is spawned.
+fn check_var_name(key: &str) -> Result<()> {
+    if key.is_empty() || key.contains('=') || key.contains('\0') {
+        bail!("Invalid environment variable name '{}'", key);
+    }
+    Ok(())
+}
+
+/// Write `contents` to a file only the user can read, replacing it in one
+/// step so a crash never leaves the secrets half written.
+fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    // The mode only applies to files we create
+    let _ = fs::remove_file(&tmp);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&tmp)
+        .with_context(|| format!("Failed to open {}", tmp.display()))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Secrets written by hand or by an older version may be readable by
+/// others; take that back before reading them.
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Ok(metadata) = fs::metadata(path) else {
+        return;
+    };
+    if metadata.permissions().mode() & 0o077 == 0 {
+        return;
+    }
+    warn!(
+        "{} was readable by other users; restricting it",
+        path.display()
+    );
+    if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(0o600)) {
+        warn!("Failed to restrict {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("voxcode-profiles-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn store() -> ProfileStore {
+        let mut env = Vars::new();
+        env.insert("HTTPS_PROXY".to_string(), "http://proxy:8080".to_string());
+        let mut secrets = Vars::new();
+        secrets.insert("API_KEY".to_string(), "secret".to_string());
+        ProfileStore {
+            dir: temp_dir(),
+            file: ProfilesFile {
+                default_profile: None,
+                profiles: BTreeMap::from([("client".to_string(), env)]),
+            },
+            secrets: BTreeMap::from([("client".to_string(), secrets)]),
+            load_error: None,
+        }
+    }
+
+    #[test]
+    fn env_includes_secrets() {
+        let env = store().env("client").unwrap();
+        assert_eq!(env["HTTPS_PROXY"], "http://proxy:8080");
+        assert_eq!(env["API_KEY"], "secret");
+        assert!(store().env("other").is_err());
+    }
+
+    #[test]
+    fn list_shows_secret_names_only() {
+        let list = store().list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].secrets, ["API_KEY"]);
+        let json = serde_json::to_string(&list).unwrap();
+        assert!(!json.contains("\"secret\""));
+    }
+
+    #[test]
+    fn unreadable_file_is_reported_not_emptied() {
+        let path =
+            std::env::temp_dir().join(format!("voxcode-secrets-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, "{ not json").unwrap();
+        let mut error = None;
+        let secrets: BTreeMap<String, Vars> = load_json(&path, &mut error);
+        fs::remove_file(&path).unwrap();
+        assert!(secrets.is_empty());
+        assert!(error.unwrap().contains(&path.display().to_string()));
+
+        let mut error = None;
+        let missing: BTreeMap<String, Vars> = load_json(&path, &mut error);
+        assert!(missing.is_empty());
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn load_error_blocks_saving_and_launching() {
+        let mut store = store();
+        store.load_error = Some("Failed to read secrets.json".to_string());
+        let saved = store.save_profile("new", Vars::new(), BTreeMap::new());
+        assert!(saved.unwrap_err().to_string().contains("secrets.json"));
+        assert!(store.remove("client").is_err());
+        assert!(store.contains("client"));
+        assert!(store.env("client").is_err());
+    }
+
+    #[test]
+    fn failed_save_leaves_profiles_unchanged() {
+        let mut store = store();
+        // A file where the directory should be makes every save fail
+        fs::write(&store.dir, "").unwrap();
+        assert!(store.remove("client").is_err());
+        assert!(store.contains("client"));
+        assert!(store.set_default_profile(Some("client".into())).is_err());
+        assert_eq!(store.default_profile(), None);
+        assert!(store
+            .save_profile("new", Vars::new(), BTreeMap::new())
+            .is_err());
+        assert!(!store.contains("new"));
+        fs::remove_file(&store.dir).unwrap();
+
+        store
+            .save_profile("new", Vars::new(), BTreeMap::new())
+            .unwrap();
+        let loaded = ProfileStore::load_from(store.dir.clone());
+        fs::remove_dir_all(&store.dir).unwrap();
+        assert!(loaded.contains("new"));
+        assert_eq!(loaded.env("client").unwrap()["API_KEY"], "secret");
+    }
+
+    #[test]
+    fn rejects_invalid_variables() {
+        let mut store = store();
+        let mut env = Vars::new();
+        env.insert("BAD=NAME".to_string(), "x".to_string());
+        let error = store
+            .save_profile("client", env, BTreeMap::new())
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid environment variable"));
+
+        let mut env = Vars::new();
+        env.insert("API_KEY".to_string(), "x".to_string());
+        let secrets = BTreeMap::from([("API_KEY".to_string(), None)]);
+        let error = store.save_profile("client", env, secrets).unwrap_err();
+        assert!(error.to_string().contains("both a plain and a secret"));
+        assert!(!store.dir.exists());
+    }
+}
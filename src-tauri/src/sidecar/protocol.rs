@@ -1,5 +1,13 @@
 use serde::{Deserialize, Serialize};
 
+use super::sdk::SdkMessage;
+
+/// Version of the stdin/stdout protocol this app speaks. Bump it for any
+/// change an older sidecar would misread.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest sidecar protocol version this app still understands.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
 // Messages sent from Rust to the sidecar (via stdin)
 #[derive(Debug, Serialize, Clone)]
 #[serde(tag = "type")]
@@ -21,15 +29,55 @@ pub enum ToSidecar {
     },
     #[serde(rename = "interrupt")]
     Interrupt,
+    /// Reply to the sidecar's `Hello`; rejected if the sidecar no longer
+    /// supports this version.
+    #[serde(rename = "hello")]
+    Hello {
+        #[serde(rename = "protocolVersion")]
+        protocol_version: u32,
+    },
+    /// Abort the running query and exit.
+    #[serde(rename = "shutdown")]
+    Shutdown,
+}
+
+impl ToSidecar {
+    /// Capability the sidecar must advertise in its `Hello` before this
+    /// message may be sent, for messages added after the first protocol.
+    pub fn required_capability(&self) -> Option<&'static str> {
+        match self {
+            Self::SetPermissionMode { .. } => Some("permissionModes"),
+            Self::Interrupt => Some("interrupt"),
+            Self::Shutdown => Some("shutdown"),
+            _ => None,
+        }
+    }
+}
+
+/// A `ToSidecar` message as written to stdin, with the id the sidecar
+/// answers with an `Ack` or `Reject`.
+#[derive(Debug, Serialize)]
+pub struct Request<'a> {
+    pub id: u64,
+    #[serde(flatten)]
+    pub message: &'a ToSidecar,
 }
 
 // Messages received from the sidecar (via stdout)
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "type")]
 pub enum FromSidecar {
+    /// Always the first message a sidecar writes.
+    #[serde(rename = "hello")]
+    Hello {
+        #[serde(rename = "protocolVersion")]
+        protocol_version: u32,
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
     #[serde(rename = "sdk_message")]
     SdkMessage {
-        message: serde_json::Value,
+        message: SdkMessage,
     },
     #[serde(rename = "permission_request")]
     PermissionRequest {
@@ -56,4 +104,13 @@ pub enum FromSidecar {
     Error {
         message: String,
     },
+    #[serde(rename = "ack")]
+    Ack {
+        id: u64,
+    },
+    #[serde(rename = "reject")]
+    Reject {
+        id: u64,
+        message: String,
+    },
 }
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Delay before the first restart; doubled for each crash in a row.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// A sidecar that stays up this long is considered healthy again, so the
+/// next crash starts over at the initial backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+/// More than this many crashes within `CRASH_WINDOW` is a crash loop, and
+/// restarting stops.
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SidecarState {
+    Running,
+    /// Exited unexpectedly; a restart is scheduled.
+    Restarting,
+    /// Crashed too often; no more restarts until the session is reopened.
+    Failed,
+    /// Shut down on purpose.
+    Stopped,
+}
+
+/// Payload of the `sidecar-status` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarStatus {
+    pub state: SidecarState,
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process, on Unix.
+    pub signal: Option<i32>,
+    /// Last lines the sidecar wrote to stderr before exiting.
+    pub stderr: Vec<String>,
+    pub restart_in_ms: Option<u64>,
+    /// Restarts since the sidecar was last stable.
+    pub attempt: u32,
+}
+
+impl SidecarStatus {
+    pub fn running(attempt: u32) -> Self {
+        Self {
+            state: SidecarState::Running,
+            exit_code: None,
+            signal: None,
+            stderr: Vec::new(),
+            restart_in_ms: None,
+            attempt,
+        }
+    }
+}
+
+/// Decides whether and when to restart a sidecar that exited unexpectedly.
+pub struct RestartTracker {
+    started_at: Instant,
+    /// Consecutive crashes without a stable run in between.
+    attempt: u32,
+    crashes: VecDeque<Instant>,
+}
+
+impl RestartTracker {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            attempt: 0,
+            crashes: VecDeque::new(),
+        }
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Note that the process was (re)started.
+    pub fn started(&mut self) {
+        self.started_at = Instant::now();
+    }
+
+    /// Record a crash and return the delay before restarting, or `None` if
+    /// the sidecar is crash-looping and should be left down.
+    pub fn crashed(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        if now.duration_since(self.started_at) >= STABLE_AFTER {
+            self.attempt = 0;
+        }
+
+        self.crashes.push_back(now);
+        while self
+            .crashes
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > CRASH_WINDOW)
+        {
+            self.crashes.pop_front();
+        }
+        if self.crashes.len() > MAX_CRASHES {
+            return None;
+        }
+
+        let backoff = INITIAL_BACKOFF
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(MAX_BACKOFF);
+        self.attempt += 1;
+        Some(backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ago(duration: Duration) -> Instant {
+        Instant::now().checked_sub(duration).unwrap()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let mut tracker = RestartTracker::new();
+        let mut delays = Vec::new();
+        for _ in 0..MAX_CRASHES {
+            delays.push(tracker.crashed().unwrap());
+            // Spread crashes out so the crash loop check stays quiet
+            tracker.crashes.clear();
+        }
+        assert_eq!(
+            delays,
+            [500, 1_000, 2_000, 4_000, 8_000].map(Duration::from_millis)
+        );
+        assert_eq!(tracker.attempt(), 5);
+
+        tracker.attempt = 10;
+        assert_eq!(tracker.crashed(), Some(MAX_BACKOFF));
+    }
+
+    #[test]
+    fn stable_run_resets_the_backoff() {
+        let mut tracker = RestartTracker::new();
+        tracker.crashed();
+        tracker.crashed();
+        assert_eq!(tracker.attempt(), 2);
+
+        tracker.started_at = ago(STABLE_AFTER);
+        assert_eq!(tracker.crashed(), Some(INITIAL_BACKOFF));
+        assert_eq!(tracker.attempt(), 1);
+    }
+
+    #[test]
+    fn crash_loop_stops_restarting() {
+        let mut tracker = RestartTracker::new();
+        for _ in 0..MAX_CRASHES {
+            assert!(tracker.crashed().is_some());
+        }
+        assert_eq!(tracker.crashed(), None);
+    }
+
+    #[test]
+    fn old_crashes_leave_the_window() {
+        let mut tracker = RestartTracker::new();
+        let old = ago(CRASH_WINDOW + Duration::from_secs(1));
+        tracker.crashes = std::iter::repeat_n(old, MAX_CRASHES).collect();
+        assert!(tracker.crashed().is_some());
+        assert_eq!(tracker.crashes.len(), 1);
+    }
+}
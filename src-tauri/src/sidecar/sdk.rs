@@ -0,0 +1,506 @@
+//! Typed view of the messages the Agent SDK streams through the sidecar.
+//!
+//! Only the fields the backend reasons about are typed; everything else is
+//! kept in each struct's `extra` map, and messages or content blocks of a
+//! type we do not know (or that fail to parse) are kept whole as `Unknown`,
+//! so re-serializing for the frontend does not lose anything.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdkMessage {
+    System(SystemMessage),
+    Assistant(AssistantMessage),
+    User(UserMessage),
+    Result(ResultMessage),
+    StreamEvent(StreamEvent),
+    Unknown(Value),
+}
+
+/// Session metadata, e.g. the `init` message with model, cwd and tools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemMessage {
+    pub subtype: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssistantMessage {
+    pub message: ApiMessage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// User turns, including the tool results the SDK feeds back to the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserMessage {
+    pub message: ApiMessage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The final message of a query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultMessage {
+    /// `success`, or the kind of error, e.g. `error_max_turns`.
+    pub subtype: String,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_turns: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_cost_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A raw streaming event from the API, sent when partial messages are on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamEvent {
+    pub event: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The API-level message inside an assistant or user message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiMessage {
+    pub content: MessageContent,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Message and tool result content: plain text or a list of blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentBlock {
+    Text(TextBlock),
+    Thinking(ThinkingBlock),
+    ToolUse(ToolUseBlock),
+    ToolResult(ToolResultBlock),
+    Unknown(Value),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextBlock {
+    pub text: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThinkingBlock {
+    pub thinking: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolUseBlock {
+    pub id: String,
+    pub name: String,
+    pub input: Value,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResultBlock {
+    pub tool_use_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<MessageContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl AssistantMessage {
+    /// Set when the message comes from a subagent's tool call. Left in
+    /// `extra`, where a `null` and a missing field stay as they were.
+    pub fn parent_tool_use_id(&self) -> Option<&str> {
+        self.extra.get("parent_tool_use_id").and_then(Value::as_str)
+    }
+
+    /// The text blocks joined by blank lines, or `None` if there is no
+    /// text, e.g. for a message that only calls tools.
+    pub fn text(&self) -> Option<String> {
+        let text = self
+            .message
+            .content
+            .blocks()
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(block) => Some(block.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        (!text.trim().is_empty()).then_some(text)
+    }
+
+    pub fn tool_uses(&self) -> impl Iterator<Item = &ToolUseBlock> {
+        self.message
+            .content
+            .blocks()
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse(block) => Some(block),
+                _ => None,
+            })
+    }
+}
+
+impl UserMessage {
+    pub fn tool_results(&self) -> impl Iterator<Item = &ToolResultBlock> {
+        self.message
+            .content
+            .blocks()
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolResult(block) => Some(block),
+                _ => None,
+            })
+    }
+}
+
+impl MessageContent {
+    /// The content blocks; plain text content has none.
+    pub fn blocks(&self) -> &[ContentBlock] {
+        match self {
+            Self::Text(_) => &[],
+            Self::Blocks(blocks) => blocks,
+        }
+    }
+}
+
+// The tagged forms used on the wire. `SdkMessage` and `ContentBlock` go
+// through these so a shape that fails to parse ends up as `Unknown`
+// instead of failing the whole message.
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TaggedMessage {
+    System(SystemMessage),
+    Assistant(AssistantMessage),
+    User(UserMessage),
+    Result(ResultMessage),
+    StreamEvent(StreamEvent),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TaggedMessageRef<'a> {
+    System(&'a SystemMessage),
+    Assistant(&'a AssistantMessage),
+    User(&'a UserMessage),
+    Result(&'a ResultMessage),
+    StreamEvent(&'a StreamEvent),
+}
+
+impl<'de> Deserialize<'de> for SdkMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(match TaggedMessage::deserialize(&value) {
+            Ok(TaggedMessage::System(m)) => Self::System(m),
+            Ok(TaggedMessage::Assistant(m)) => Self::Assistant(m),
+            Ok(TaggedMessage::User(m)) => Self::User(m),
+            Ok(TaggedMessage::Result(m)) => Self::Result(m),
+            Ok(TaggedMessage::StreamEvent(m)) => Self::StreamEvent(m),
+            Err(_) => Self::Unknown(value),
+        })
+    }
+}
+
+impl Serialize for SdkMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::System(m) => TaggedMessageRef::System(m).serialize(serializer),
+            Self::Assistant(m) => TaggedMessageRef::Assistant(m).serialize(serializer),
+            Self::User(m) => TaggedMessageRef::User(m).serialize(serializer),
+            Self::Result(m) => TaggedMessageRef::Result(m).serialize(serializer),
+            Self::StreamEvent(m) => TaggedMessageRef::StreamEvent(m).serialize(serializer),
+            Self::Unknown(value) => value.serialize(serializer),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TaggedBlock {
+    Text(TextBlock),
+    Thinking(ThinkingBlock),
+    ToolUse(ToolUseBlock),
+    ToolResult(ToolResultBlock),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TaggedBlockRef<'a> {
+    Text(&'a TextBlock),
+    Thinking(&'a ThinkingBlock),
+    ToolUse(&'a ToolUseBlock),
+    ToolResult(&'a ToolResultBlock),
+}
+
+impl<'de> Deserialize<'de> for ContentBlock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(match TaggedBlock::deserialize(&value) {
+            Ok(TaggedBlock::Text(b)) => Self::Text(b),
+            Ok(TaggedBlock::Thinking(b)) => Self::Thinking(b),
+            Ok(TaggedBlock::ToolUse(b)) => Self::ToolUse(b),
+            Ok(TaggedBlock::ToolResult(b)) => Self::ToolResult(b),
+            Err(_) => Self::Unknown(value),
+        })
+    }
+}
+
+impl Serialize for ContentBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Text(b) => TaggedBlockRef::Text(b).serialize(serializer),
+            Self::Thinking(b) => TaggedBlockRef::Thinking(b).serialize(serializer),
+            Self::ToolUse(b) => TaggedBlockRef::ToolUse(b).serialize(serializer),
+            Self::ToolResult(b) => TaggedBlockRef::ToolResult(b).serialize(serializer),
+            Self::Unknown(value) => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Parse `value`, check it with `check`, and make sure it serializes
+    /// back to exactly the same JSON.
+    fn round_trip<T>(value: Value, check: impl FnOnce(&T) -> bool) -> T
+    where
+        T: Serialize + for<'de> Deserialize<'de> + std::fmt::Debug,
+    {
+        let parsed: T = serde_json::from_value(value.clone()).unwrap();
+        assert!(check(&parsed), "unexpected parse: {:?}", parsed);
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), value);
+        parsed
+    }
+
+    #[test]
+    fn system_message_round_trips() {
+        let message = round_trip(
+            json!({
+                "type": "system",
+                "subtype": "init",
+                "session_id": "abc",
+                "model": "some-model",
+                "tools": ["Read", "Edit"],
+            }),
+            |m| matches!(m, SdkMessage::System(_)),
+        );
+        let SdkMessage::System(system) = message else {
+            unreachable!()
+        };
+        assert_eq!(system.subtype, "init");
+        assert_eq!(system.extra["model"], "some-model");
+    }
+
+    #[test]
+    fn assistant_message_round_trips() {
+        let message = round_trip(
+            json!({
+                "type": "assistant",
+                "message": {
+                    "id": "msg_1",
+                    "role": "assistant",
+                    "content": [
+                        {"type": "thinking", "thinking": "Look first", "signature": "sig"},
+                        {"type": "text", "text": "Reading it."},
+                        {"type": "tool_use", "id": "tu_1", "name": "Read", "input": {"path": "a.rs"}},
+                        {"type": "text", "text": "Done."},
+                    ],
+                },
+                "parent_tool_use_id": null,
+                "session_id": "abc",
+            }),
+            |m| matches!(m, SdkMessage::Assistant(a) if a.parent_tool_use_id().is_none()),
+        );
+        let SdkMessage::Assistant(assistant) = message else {
+            unreachable!()
+        };
+        assert_eq!(assistant.text().as_deref(), Some("Reading it.\n\nDone."));
+        let tools: Vec<&str> = assistant.tool_uses().map(|t| t.name.as_str()).collect();
+        assert_eq!(tools, ["Read"]);
+    }
+
+    #[test]
+    fn assistant_message_without_text_has_none() {
+        let message: AssistantMessage = serde_json::from_value(json!({
+            "message": {"content": [{"type": "tool_use", "id": "tu_1", "name": "Bash", "input": {}}]},
+        }))
+        .unwrap();
+        assert_eq!(message.text(), None);
+    }
+
+    #[test]
+    fn user_message_round_trips() {
+        let message = round_trip(
+            json!({
+                "type": "user",
+                "message": {
+                    "role": "user",
+                    "content": [
+                        {"type": "tool_result", "tool_use_id": "tu_1", "content": "fn main() {}", "is_error": false},
+                        {
+                            "type": "tool_result",
+                            "tool_use_id": "tu_2",
+                            "content": [{"type": "text", "text": "No such file"}],
+                            "is_error": true,
+                        },
+                    ],
+                },
+                "parent_tool_use_id": "tu_0",
+            }),
+            |m| matches!(m, SdkMessage::User(u) if u.extra["parent_tool_use_id"] == "tu_0"),
+        );
+        let SdkMessage::User(user) = message else {
+            unreachable!()
+        };
+        let errors: Vec<Option<bool>> = user.tool_results().map(|r| r.is_error).collect();
+        assert_eq!(errors, [Some(false), Some(true)]);
+    }
+
+    #[test]
+    fn missing_optional_fields_stay_missing() {
+        round_trip(
+            json!({
+                "type": "user",
+                "message": {
+                    "role": "user",
+                    "content": [{"type": "tool_result", "tool_use_id": "tu_1", "content": "ok"}],
+                },
+            }),
+            |m| matches!(m, SdkMessage::User(u) if u.tool_results().all(|r| r.is_error.is_none())),
+        );
+        round_trip(
+            json!({
+                "type": "assistant",
+                "message": {"role": "assistant", "content": [{"type": "text", "text": "Hi"}]},
+            }),
+            |m| matches!(m, SdkMessage::Assistant(a) if a.parent_tool_use_id().is_none()),
+        );
+
+        round_trip(
+            json!({"type": "stream_event", "event": {"type": "message_stop"}}),
+            |m| matches!(m, SdkMessage::StreamEvent(_)),
+        );
+    }
+
+    #[test]
+    fn user_message_with_plain_text_round_trips() {
+        round_trip(
+            json!({
+                "type": "user",
+                "message": {"role": "user", "content": "Hello"},
+                "parent_tool_use_id": null,
+            }),
+            |m| matches!(m, SdkMessage::User(u) if u.message.content == MessageContent::Text("Hello".to_string())),
+        );
+    }
+
+    #[test]
+    fn result_message_round_trips() {
+        round_trip(
+            json!({
+                "type": "result",
+                "subtype": "success",
+                "is_error": false,
+                "result": "All done",
+                "num_turns": 3,
+                "duration_ms": 1200,
+                "total_cost_usd": 0.05,
+                "session_id": "abc",
+                "usage": {"input_tokens": 10},
+            }),
+            |m| matches!(m, SdkMessage::Result(r) if r.num_turns == Some(3)),
+        );
+    }
+
+    #[test]
+    fn stream_event_round_trips() {
+        round_trip(
+            json!({
+                "type": "stream_event",
+                "event": {"type": "content_block_delta", "delta": {"text": "Hi"}},
+                "parent_tool_use_id": null,
+                "uuid": "u1",
+            }),
+            |m| matches!(m, SdkMessage::StreamEvent(_)),
+        );
+    }
+
+    #[test]
+    fn unknown_messages_are_kept_whole() {
+        // A type we do not know
+        round_trip(json!({"type": "rate_limit", "retry_after": 5}), |m| {
+            matches!(m, SdkMessage::Unknown(_))
+        });
+        // A known type with a shape we cannot parse
+        round_trip(
+            json!({"type": "assistant", "message": "not an object"}),
+            |m| matches!(m, SdkMessage::Unknown(_)),
+        );
+    }
+
+    #[test]
+    fn content_blocks_round_trip() {
+        round_trip(
+            json!({"type": "text", "text": "Hi", "citations": []}),
+            |b| matches!(b, ContentBlock::Text(_)),
+        );
+        round_trip(json!({"type": "thinking", "thinking": "Hmm"}), |b| {
+            matches!(b, ContentBlock::Thinking(_))
+        });
+        round_trip(
+            json!({"type": "tool_use", "id": "tu_1", "name": "Edit", "input": {"path": "a.rs"}}),
+            |b| matches!(b, ContentBlock::ToolUse(t) if t.name == "Edit"),
+        );
+        round_trip(
+            json!({"type": "tool_result", "tool_use_id": "tu_1", "is_error": false}),
+            |b| matches!(b, ContentBlock::ToolResult(r) if r.content.is_none()),
+        );
+    }
+
+    #[test]
+    fn unknown_content_blocks_are_kept_whole() {
+        round_trip(
+            json!({"type": "image", "source": {"type": "base64", "data": "AAAA"}}),
+            |b| matches!(b, ContentBlock::Unknown(_)),
+        );
+        round_trip(json!({"type": "text"}), |b| {
+            matches!(b, ContentBlock::Unknown(_))
+        });
+    }
+}
@@ -0,0 +1,282 @@
+//! Agent sessions. Each session has its own sidecar process, so sessions
+//! keep independent working directories, permission modes and turns.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use super::launch::{self, LaunchError, LaunchFailure, SidecarLaunch};
+use super::manager::{SidecarManager, STOP_TIMEOUT};
+use crate::state::AppState;
+
+/// The session started with the app, used until the UI opens another.
+pub const DEFAULT_SESSION_ID: &str = "default";
+
+/// Payload of every event a session's sidecar emits.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEvent<'a, T> {
+    pub session_id: &'a str,
+    pub payload: T,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub cwd: Option<String>,
+    pub permission_mode: String,
+    /// Environment profile the sidecar is started with.
+    pub profile: Option<String>,
+    pub running: bool,
+    pub generating: bool,
+    pub active: bool,
+}
+
+pub struct SessionRegistry {
+    sessions: HashMap<String, Arc<SidecarManager>>,
+    /// The session voice commands, barge-in and speech follow.
+    active: String,
+    /// How new sidecars are spawned, or why they cannot be. `None` until
+    /// the launch has been resolved.
+    launch: Option<Result<SidecarLaunch, LaunchError>>,
+}
+
+impl SessionRegistry {
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            active: DEFAULT_SESSION_ID.to_string(),
+            launch: None,
+        }
+    }
+
+    pub fn set_launch(&mut self, launch: Result<SidecarLaunch, LaunchError>) {
+        self.launch = Some(launch);
+    }
+
+    /// Start a sidecar for `session_id`, with the default environment
+    /// profile unless given one. Opening a session that exists updates its
+    /// working directory, and spawns its sidecar again if it is not running,
+    /// e.g. after a failed spawn or a crash loop.
+    pub fn open(
+        &mut self,
+        app_handle: &AppHandle,
+        session_id: &str,
+        cwd: Option<String>,
+        profile: Option<String>,
+    ) -> Result<Arc<SidecarManager>> {
+        if let Some(sidecar) = self.sessions.get(session_id).cloned() {
+            if let Some(cwd) = cwd {
+                sidecar.set_cwd(cwd);
+            }
+            if !sidecar.is_running() {
+                info!("Spawning the sidecar of session {} again", session_id);
+                sidecar.spawn(self.launch()?, app_handle.clone())?;
+            }
+            return Ok(sidecar);
+        }
+
+        let launch = self.launch()?;
+        let state = app_handle.state::<AppState>();
+        let profiles = state.profiles.lock().unwrap();
+        let profile = match profile {
+            Some(name) if !profiles.contains(&name) => {
+                bail!("No environment profile '{}'", name)
+            }
+            Some(name) => Some(name),
+            None => profiles.default_profile(),
+        };
+        drop(profiles);
+        info!(
+            "Opening session {} in {:?} with profile {:?}",
+            session_id, cwd, profile
+        );
+        let sidecar = SidecarManager::new(session_id, cwd, profile);
+        // Registered even if the spawn fails, so the session reports itself
+        // as not running instead of unknown
+        self.sessions
+            .insert(session_id.to_string(), sidecar.clone());
+        sidecar.spawn(launch, app_handle.clone())?;
+        Ok(sidecar)
+    }
+
+    fn launch(&self) -> Result<SidecarLaunch> {
+        match &self.launch {
+            Some(Ok(launch)) => Ok(launch.clone()),
+            Some(Err(e)) => bail!("{}", e),
+            None => bail!("The sidecar has not been checked yet"),
+        }
+    }
+
+    /// Switch a session to another environment profile, or to none. A
+    /// running sidecar is restarted so the profile takes effect.
+    pub fn set_profile(
+        &self,
+        app_handle: &AppHandle,
+        session_id: &str,
+        profile: Option<String>,
+    ) -> Result<()> {
+        let sidecar = self.get(session_id)?;
+        if let Some(name) = &profile {
+            if !app_handle
+                .state::<AppState>()
+                .profiles
+                .lock()
+                .unwrap()
+                .contains(name)
+            {
+                bail!("No environment profile '{}'", name);
+            }
+        }
+        if sidecar.profile() == profile {
+            return Ok(());
+        }
+        if sidecar.is_generating() {
+            bail!("Wait for the current turn to finish before switching profiles");
+        }
+        info!("Session {} now uses profile {:?}", session_id, profile);
+        sidecar.set_profile(profile);
+        if sidecar.is_running() {
+            sidecar.restart(app_handle.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Sessions started with `profile`, which must not lose it.
+    pub fn using_profile(&self, profile: &str) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, sidecar)| sidecar.profile().as_deref() == Some(profile))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Stop a session's sidecar and forget it. The active session falls
+    /// back to the default one. The sidecar is returned so the caller can
+    /// release it after letting go of the registry's lock.
+    pub fn close(&mut self, session_id: &str) -> Result<Arc<SidecarManager>> {
+        if session_id == DEFAULT_SESSION_ID {
+            bail!("The default session cannot be closed");
+        }
+        let sidecar = self
+            .sessions
+            .remove(session_id)
+            .ok_or_else(|| anyhow!("No session '{}'", session_id))?;
+        info!("Closing session {}", session_id);
+        sidecar.kill()?;
+        if self.active == session_id {
+            self.active = DEFAULT_SESSION_ID.to_string();
+        }
+        Ok(sidecar)
+    }
+
+    /// Stop every sidecar, returning their supervisors to wait on.
+    pub fn stop_all(&mut self) -> Vec<JoinHandle<()>> {
+        self.sessions
+            .values()
+            .filter_map(|sidecar| sidecar.stop())
+            .collect()
+    }
+
+    pub fn get(&self, session_id: &str) -> Result<Arc<SidecarManager>> {
+        self.sessions
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("No session '{}'", session_id))
+    }
+
+    pub fn active(&self) -> Result<Arc<SidecarManager>> {
+        self.get(&self.active)
+    }
+
+    pub fn active_id(&self) -> &str {
+        &self.active
+    }
+
+    pub fn set_active(&mut self, session_id: &str) -> Result<()> {
+        self.get(session_id)?;
+        self.active = session_id.to_string();
+        Ok(())
+    }
+
+    pub fn info(&self, session_id: &str) -> Result<SessionInfo> {
+        let sidecar = self.get(session_id)?;
+        Ok(SessionInfo {
+            session_id: session_id.to_string(),
+            cwd: sidecar.cwd(),
+            permission_mode: sidecar.permission_mode(),
+            profile: sidecar.profile(),
+            running: sidecar.is_running(),
+            generating: sidecar.is_generating(),
+            active: self.active == session_id,
+        })
+    }
+
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let mut ids: Vec<&String> = self.sessions.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|id| self.info(id).ok())
+            .collect()
+    }
+}
+
+/// Stop every session's sidecar and wait for them to exit, so none outlives
+/// the app and each logs how it ended. Only for app exit: it blocks for up
+/// to `STOP_TIMEOUT`.
+pub fn shut_down_all(app_handle: &AppHandle) {
+    let supervisors = app_handle
+        .state::<AppState>()
+        .sessions
+        .lock()
+        .unwrap()
+        .stop_all();
+    if supervisors.is_empty() {
+        return;
+    }
+    tauri::async_runtime::block_on(async move {
+        let exited = async {
+            for supervisor in supervisors {
+                let _ = supervisor.await;
+            }
+        };
+        if tokio::time::timeout(STOP_TIMEOUT, exited).await.is_err() {
+            warn!("Sidecars had not exited when the app shut down");
+        }
+    });
+}
+
+/// Resolve the sidecar launch from the current settings for sessions opened
+/// from now on, and open the default session if it was waiting for a usable
+/// sidecar. A failure is also emitted as `sidecar-launch-error`. Runs the
+/// runtime to check its version, so keep it off the main thread.
+pub fn refresh_launch(app_handle: &AppHandle) -> Result<SidecarLaunch, LaunchError> {
+    let state = app_handle.state::<AppState>();
+    let config = state.settings.lock().unwrap().sidecar.clone();
+    let launch = launch::resolve_for_app(app_handle, &config);
+    match &launch {
+        Ok(launch) => info!("Sidecar launch: {}", launch),
+        Err(e) => {
+            error!("No usable sidecar: {}", e);
+            let _ = app_handle.emit("sidecar-launch-error", LaunchFailure::from(e.clone()));
+        }
+    }
+
+    let mut sessions = state.sessions.lock().unwrap();
+    sessions.set_launch(launch.clone());
+    if launch.is_ok() {
+        if let Err(e) = sessions.open(app_handle, DEFAULT_SESSION_ID, None, None) {
+            error!("Failed to spawn sidecar: {}", e);
+        }
+    }
+    launch
+}
@@ -1,18 +1,44 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
+use crate::audio::aec::EchoReference;
+use crate::audio::earcons::CuePlayer;
+use crate::audio::playback::AudioPlayer;
+use crate::audio::tts::Speaker;
 use crate::audio::AudioPipeline;
-use crate::sidecar::manager::SidecarManager;
+use crate::settings::Settings;
+use crate::sidecar::profiles::ProfileStore;
+use crate::sidecar::session::SessionRegistry;
+use crate::voice::corrections::CorrectionStore;
+use crate::voice::PendingVoiceCommand;
 
 pub struct AppState {
-    pub sidecar: Mutex<SidecarManager>,
+    pub sessions: Mutex<SessionRegistry>,
+    pub profiles: Mutex<ProfileStore>,
     pub audio: Mutex<AudioPipeline>,
+    pub settings: Mutex<Settings>,
+    pub pending_voice_command: Mutex<Option<PendingVoiceCommand>>,
+    pub corrections: Mutex<CorrectionStore>,
+    /// Id of the draft transcript awaiting a refinement pass, if any.
+    pub pending_draft: Mutex<Option<String>>,
+    pub speaker: Mutex<Speaker>,
+    pub cues: Mutex<CuePlayer>,
 }
 
 impl AppState {
     pub fn new() -> Self {
+        let settings = Settings::load();
+        let echo_reference = EchoReference::new();
+        let player = Arc::new(AudioPlayer::new(echo_reference.clone()));
         Self {
-            sidecar: Mutex::new(SidecarManager::new()),
-            audio: Mutex::new(AudioPipeline::new()),
+            sessions: Mutex::new(SessionRegistry::new()),
+            profiles: Mutex::new(ProfileStore::load()),
+            audio: Mutex::new(AudioPipeline::new(echo_reference)),
+            corrections: Mutex::new(CorrectionStore::load(settings.corrections.clone())),
+            pending_voice_command: Mutex::new(None),
+            pending_draft: Mutex::new(None),
+            speaker: Mutex::new(Speaker::new(settings.tts.clone(), player.clone())),
+            cues: Mutex::new(CuePlayer::new(settings.earcons.clone(), player)),
+            settings: Mutex::new(settings),
         }
     }
 }
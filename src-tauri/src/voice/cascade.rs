@@ -0,0 +1,64 @@
+use std::thread;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info};
+
+use crate::audio::RefineJob;
+use crate::state::AppState;
+
+/// Payload of the `transcription-refined` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefinedTranscript {
+    pub id: String,
+    /// The draft this replaces, so the UI can leave user edits alone.
+    pub draft: String,
+    pub text: String,
+}
+
+/// Re-transcribe the last recording with the larger model and emit the
+/// result, unless the draft has been sent or superseded in the meantime.
+pub fn spawn_refinement(app_handle: AppHandle, job: RefineJob, draft: String) {
+    let id = uuid::Uuid::new_v4().to_string();
+    *app_handle.state::<AppState>().pending_draft.lock().unwrap() = Some(id.clone());
+
+    thread::spawn(move || {
+        let state = app_handle.state::<AppState>();
+        let refined = match job.engine.transcribe(&job.audio, &job.options) {
+            Ok(refined) => refined,
+            Err(e) => {
+                error!("Refinement pass failed: {}", e);
+                return;
+            }
+        };
+        let text = super::postprocess(&state, &refined.text);
+
+        let mut pending = state.pending_draft.lock().unwrap();
+        if pending.as_deref() != Some(id.as_str()) {
+            info!("Draft already sent or replaced; dropping refinement");
+            return;
+        }
+        *pending = None;
+        drop(pending);
+
+        if text.is_empty() || text == draft {
+            return;
+        }
+        info!("Refined transcription: {:?}", text);
+        state
+            .corrections
+            .lock()
+            .unwrap()
+            .set_last_transcript(&job.session_id, &text);
+        let _ = app_handle.emit(
+            "transcription-refined",
+            RefinedTranscript { id, draft, text },
+        );
+    });
+}
+
+/// Forget the outstanding draft so a late refinement is not emitted.
+pub fn discard_draft(state: &AppState) {
+    state.pending_draft.lock().unwrap().take();
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::settings::voxcode_dir;
+
+/// Longest phrase, in words, that is learned as a single substitution.
+const MAX_PHRASE_WORDS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CorrectionConfig {
+    pub enabled: bool,
+    /// How often the same edit must be seen before it is applied automatically.
+    pub min_occurrences: u32,
+    /// Skip learning when the sent text shares less than this fraction of
+    /// words with the transcript, since the user most likely rewrote it.
+    pub min_similarity: f32,
+}
+
+impl Default for CorrectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_occurrences: 2,
+            min_similarity: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Correction {
+    pub from: String,
+    pub to: String,
+    pub count: u32,
+}
+
+/// Substitutions learned from edits users make to transcripts before
+/// sending, persisted in `~/.voxcode/corrections.json`.
+pub struct CorrectionStore {
+    config: CorrectionConfig,
+    /// Keyed by the normalized `from` phrase.
+    corrections: HashMap<String, Correction>,
+    /// The last transcript handed to each session's input box, keyed by
+    /// session id.
+    last_transcripts: HashMap<String, String>,
+}
+
+impl CorrectionStore {
+    pub fn path() -> PathBuf {
+        voxcode_dir().join("corrections.json")
+    }
+
+    pub fn load(config: CorrectionConfig) -> Self {
+        let path = Self::path();
+        let corrections = if path.exists() {
+            match std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| {
+                    serde_json::from_str::<Vec<Correction>>(&s).map_err(anyhow::Error::from)
+                }) {
+                Ok(list) => list.into_iter().map(|c| (normalize(&c.from), c)).collect(),
+                Err(e) => {
+                    warn!("Failed to read corrections from {}: {}", path.display(), e);
+                    HashMap::new()
+                }
+            }
+        } else {
+            HashMap::new()
+        };
+
+        Self {
+            config,
+            corrections,
+            last_transcripts: HashMap::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: CorrectionConfig) {
+        self.config = config;
+    }
+
+    pub fn list(&self) -> Vec<Correction> {
+        let mut list: Vec<Correction> = self.corrections.values().cloned().collect();
+        list.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.from.cmp(&b.from)));
+        list
+    }
+
+    pub fn remove(&mut self, from: &str) -> Result<()> {
+        if self.corrections.remove(&normalize(from)).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(&self.list())?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Remember the transcript handed to a session's input box so the
+    /// edited text passed to `send_message` can be compared against it.
+    pub fn set_last_transcript(&mut self, session_id: &str, text: &str) {
+        if text.is_empty() {
+            self.last_transcripts.remove(session_id);
+        } else {
+            self.last_transcripts
+                .insert(session_id.to_string(), text.to_string());
+        }
+    }
+
+    pub fn forget_session(&mut self, session_id: &str) {
+        self.last_transcripts.remove(session_id);
+    }
+
+    /// Apply learned substitutions to a transcript. Replacements are made in
+    /// place, so line breaks and other whitespace around them survive.
+    pub fn apply(&self, text: &str) -> String {
+        if !self.config.enabled || self.corrections.is_empty() {
+            return text.to_string();
+        }
+
+        let spans = word_spans(text);
+        let keys: Vec<String> = spans
+            .iter()
+            .map(|&(start, end)| normalize(&text[start..end]))
+            .collect();
+        let mut out = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut i = 0;
+
+        'outer: while i < spans.len() {
+            for len in (1..=MAX_PHRASE_WORDS.min(spans.len() - i)).rev() {
+                let key = keys[i..i + len].join(" ");
+                if let Some(correction) = self.corrections.get(&key) {
+                    if correction.count >= self.config.min_occurrences {
+                        let (start, _) = spans[i];
+                        let (last_start, end) = spans[i + len - 1];
+                        out.push_str(&text[copied..start]);
+                        out.push_str(&correction.to);
+                        out.push_str(trailing_punctuation(&text[last_start..end]));
+                        copied = end;
+                        i += len;
+                        continue 'outer;
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        out.push_str(&text[copied..]);
+        out
+    }
+
+    /// Compare the text the user actually sent to a session with the last
+    /// transcript for it and learn any word-level substitutions between them.
+    pub fn learn_from_sent(&mut self, session_id: &str, sent: &str) -> Result<()> {
+        let Some(transcript) = self.last_transcripts.remove(session_id) else {
+            return Ok(());
+        };
+        if !self.config.enabled || transcript == sent {
+            return Ok(());
+        }
+
+        let before: Vec<&str> = transcript.split_whitespace().collect();
+        let after: Vec<&str> = sent.split_whitespace().collect();
+        let (pairs, matched) = substitutions(&before, &after);
+
+        let similarity = matched as f32 / before.len().max(after.len()).max(1) as f32;
+        if similarity < self.config.min_similarity {
+            return Ok(());
+        }
+
+        if pairs.is_empty() {
+            return Ok(());
+        }
+        for (from, to) in pairs {
+            let key = normalize(&from);
+            let entry = self.corrections.entry(key).or_insert_with(|| Correction {
+                from: from.clone(),
+                to: to.clone(),
+                count: 0,
+            });
+            // A different fix for the same phrase starts over
+            if entry.to != to {
+                entry.to = to;
+                entry.count = 0;
+            }
+            entry.count += 1;
+            info!(
+                "Learned correction {:?} -> {:?} ({}x)",
+                entry.from, entry.to, entry.count
+            );
+        }
+        self.save()
+    }
+}
+
+/// Align two word sequences by longest common subsequence and return the
+/// replaced spans as (from, to) pairs, plus the number of matched words.
+fn substitutions(before: &[&str], after: &[&str]) -> (Vec<(String, String)>, usize) {
+    let a: Vec<String> = before.iter().map(|w| normalize(w)).collect();
+    let b: Vec<String> = after.iter().map(|w| normalize(w)).collect();
+
+    // lcs[i][j] = LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut del_start, mut ins_start) = (0, 0);
+    loop {
+        let at_match = i < a.len() && j < b.len() && a[i] == b[j];
+        let at_end = i == a.len() && j == b.len();
+        if at_match || at_end {
+            let deleted = &before[del_start..i];
+            let inserted = &after[ins_start..j];
+            if !deleted.is_empty()
+                && !inserted.is_empty()
+                && deleted.len() <= MAX_PHRASE_WORDS
+                && inserted.len() <= MAX_PHRASE_WORDS
+            {
+                pairs.push((
+                    strip_punctuation(&deleted.join(" ")),
+                    strip_punctuation(&inserted.join(" ")),
+                ));
+            }
+            if at_end {
+                break;
+            }
+            i += 1;
+            j += 1;
+            del_start = i;
+            ins_start = j;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    (pairs, lcs[0][0])
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '\'' || *c == '_')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Byte ranges of the whitespace-separated words in `text`.
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+    spans
+}
+
+fn strip_punctuation(text: &str) -> String {
+    text.trim_end_matches(|c: char| ".,;:!?".contains(c))
+        .to_string()
+}
+
+fn trailing_punctuation(word: &str) -> &str {
+    let end = word.trim_end_matches(|c: char| ".,;:!?".contains(c)).len();
+    &word[end..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(corrections: &[(&str, &str, u32)]) -> CorrectionStore {
+        CorrectionStore {
+            config: CorrectionConfig::default(),
+            corrections: corrections
+                .iter()
+                .map(|(from, to, count)| {
+                    let correction = Correction {
+                        from: from.to_string(),
+                        to: to.to_string(),
+                        count: *count,
+                    };
+                    (normalize(from), correction)
+                })
+                .collect(),
+            last_transcripts: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn applies_learned_substitutions() {
+        let store = store(&[("rust lang", "Rust", 2), ("cargo", "Cargo", 3)]);
+        assert_eq!(
+            store.apply("I like rust lang and cargo."),
+            "I like Rust and Cargo."
+        );
+    }
+
+    #[test]
+    fn keeps_line_breaks_and_tabs() {
+        let store = store(&[("tokyo", "tokio", 2)]);
+        assert_eq!(
+            store.apply("use tokyo;\n\n\tfn main() {}\n"),
+            "use tokio;\n\n\tfn main() {}\n"
+        );
+        assert_eq!(store.apply("  tokyo  runtime"), "  tokio  runtime");
+    }
+
+    #[test]
+    fn matches_phrases_across_line_breaks() {
+        let store = store(&[("rust lang", "Rust", 2)]);
+        assert_eq!(store.apply("rust\nlang\nrocks"), "Rust\nrocks");
+    }
+
+    #[test]
+    fn skips_corrections_seen_too_rarely() {
+        let store = store(&[("tokyo", "tokio", 1)]);
+        assert_eq!(store.apply("tokyo"), "tokyo");
+    }
+
+    #[test]
+    fn disabled_store_leaves_text_alone() {
+        let mut store = store(&[("tokyo", "tokio", 5)]);
+        store.set_config(CorrectionConfig {
+            enabled: false,
+            ..CorrectionConfig::default()
+        });
+        assert_eq!(store.apply("tokyo"), "tokyo");
+    }
+
+    #[test]
+    fn learns_only_from_the_same_session() {
+        let mut store = store(&[]);
+        store.set_last_transcript("a", "run cargo test on tokyo");
+        store
+            .learn_from_sent("b", "run cargo test on tokio")
+            .unwrap();
+        assert!(store.list().is_empty());
+        assert!(store.last_transcripts.contains_key("a"));
+
+        store.set_last_transcript("a", "");
+        assert!(store.last_transcripts.is_empty());
+    }
+
+    #[test]
+    fn finds_replaced_spans() {
+        let (pairs, matched) = substitutions(
+            &["run", "cargo", "test", "on", "tokyo."],
+            &["run", "cargo", "test", "on", "tokio."],
+        );
+        assert_eq!(pairs, vec![("tokyo".to_string(), "tokio".to_string())]);
+        assert_eq!(matched, 4);
+    }
+
+    #[test]
+    fn ignores_insertions_and_long_rewrites() {
+        let (pairs, _) = substitutions(&["hello", "world"], &["hello", "big", "world"]);
+        assert!(pairs.is_empty());
+
+        let before = ["a", "b", "c", "d", "e", "z"];
+        let after = ["v", "w", "x", "y", "q", "z"];
+        let (pairs, matched) = substitutions(&before, &after);
+        assert!(pairs.is_empty());
+        assert_eq!(matched, 1);
+    }
+
+    #[test]
+    fn normalize_ignores_case_and_punctuation() {
+        assert_eq!(normalize("Don't  STOP, now!"), "don't stop now");
+        assert_eq!(normalize("user_id."), "user_id");
+    }
+}
@@ -0,0 +1,716 @@
+use serde::{Deserialize, Serialize};
+
+/// How spoken transcripts are rewritten before they reach the input box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DictationMode {
+    /// Natural language: larger numbers, and spoken punctuation if enabled.
+    #[default]
+    Prose,
+    /// Identifiers, symbols and spelling. Whisper's own punctuation and
+    /// capitalization are discarded in favour of what was dictated.
+    Code,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DictationConfig {
+    pub mode: DictationMode,
+    pub normalize_numbers: bool,
+    /// Turn "comma", "period" and the like into punctuation in prose mode.
+    /// Off by default, since the same words come up in ordinary speech
+    /// and Whisper already punctuates.
+    pub spoken_punctuation: bool,
+}
+
+impl Default for DictationConfig {
+    fn default() -> Self {
+        Self {
+            mode: DictationMode::Prose,
+            normalize_numbers: true,
+            spoken_punctuation: false,
+        }
+    }
+}
+
+/// Rewrite a transcript according to the dictation mode.
+pub fn process(text: &str, config: &DictationConfig) -> String {
+    let words = split_words(text);
+    match config.mode {
+        DictationMode::Prose => process_prose(&words, config),
+        DictationMode::Code => process_code(&words),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spacing {
+    /// Glued to both neighbours, e.g. `.` or `(`.
+    Attach,
+    /// Glued to the previous word, followed by a space, e.g. `,`.
+    Trailing,
+    /// Surrounded by spaces, e.g. `=`.
+    Operator,
+}
+
+/// Spoken symbols for code mode. Longer phrases must come before their
+/// prefixes so the longest match wins.
+const CODE_SYMBOLS: &[(&str, &str, Spacing)] = &[
+    ("open paren", "(", Spacing::Attach),
+    ("close paren", ")", Spacing::Attach),
+    ("open bracket", "[", Spacing::Attach),
+    ("close bracket", "]", Spacing::Attach),
+    ("open brace", "{", Spacing::Attach),
+    ("close brace", "}", Spacing::Attach),
+    ("open curly", "{", Spacing::Attach),
+    ("close curly", "}", Spacing::Attach),
+    ("open angle", "<", Spacing::Attach),
+    ("close angle", ">", Spacing::Attach),
+    ("double colon", "::", Spacing::Attach),
+    ("double equals", "==", Spacing::Operator),
+    ("not equals", "!=", Spacing::Operator),
+    ("plus equals", "+=", Spacing::Operator),
+    ("minus equals", "-=", Spacing::Operator),
+    ("less than", "<", Spacing::Operator),
+    ("greater than", ">", Spacing::Operator),
+    ("fat arrow", "=>", Spacing::Operator),
+    ("double ampersand", "&&", Spacing::Operator),
+    ("double pipe", "||", Spacing::Operator),
+    ("question mark", "?", Spacing::Attach),
+    ("exclamation mark", "!", Spacing::Attach),
+    ("double quote", "\"", Spacing::Attach),
+    ("single quote", "'", Spacing::Attach),
+    ("at sign", "@", Spacing::Attach),
+    ("dollar sign", "$", Spacing::Attach),
+    ("new line", "\n", Spacing::Attach),
+    ("dot", ".", Spacing::Attach),
+    ("comma", ",", Spacing::Trailing),
+    ("colon", ":", Spacing::Trailing),
+    ("semicolon", ";", Spacing::Trailing),
+    ("equals", "=", Spacing::Operator),
+    ("plus", "+", Spacing::Operator),
+    ("minus", "-", Spacing::Operator),
+    ("arrow", "->", Spacing::Operator),
+    ("underscore", "_", Spacing::Attach),
+    ("dash", "-", Spacing::Attach),
+    ("hyphen", "-", Spacing::Attach),
+    ("slash", "/", Spacing::Attach),
+    ("backslash", "\\", Spacing::Attach),
+    ("ampersand", "&", Spacing::Attach),
+    ("pipe", "|", Spacing::Attach),
+    ("star", "*", Spacing::Attach),
+    ("asterisk", "*", Spacing::Attach),
+    ("hash", "#", Spacing::Attach),
+    ("percent", "%", Spacing::Attach),
+    ("caret", "^", Spacing::Attach),
+    ("tilde", "~", Spacing::Attach),
+    ("bang", "!", Spacing::Attach),
+    ("quote", "\"", Spacing::Attach),
+    ("backtick", "`", Spacing::Attach),
+    ("tab", "\t", Spacing::Attach),
+    ("space", " ", Spacing::Attach),
+];
+
+/// Spoken punctuation recognized in prose mode.
+const PROSE_PUNCTUATION: &[(&str, &str)] = &[
+    ("new paragraph", "\n\n"),
+    ("new line", "\n"),
+    ("question mark", "?"),
+    ("exclamation mark", "!"),
+    ("exclamation point", "!"),
+    ("full stop", "."),
+    ("period", "."),
+    ("comma", ","),
+    ("semicolon", ";"),
+    ("colon", ":"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Casing {
+    Snake,
+    Camel,
+    Pascal,
+    Kebab,
+    Constant,
+}
+
+const CASING_COMMANDS: &[(&str, Casing)] = &[
+    ("screaming snake case", Casing::Constant),
+    ("constant case", Casing::Constant),
+    ("snake case", Casing::Snake),
+    ("camel case", Casing::Camel),
+    ("pascal case", Casing::Pascal),
+    ("kebab case", Casing::Kebab),
+];
+
+const END_CASE: &str = "end case";
+const SPELL: &str = "spell";
+const END_SPELL: &[&str] = &["end spell", "stop spelling"];
+const LITERAL: &str = "literal";
+
+/// Letter names Whisper tends to produce while spelling, plus the NATO
+/// alphabet.
+const LETTER_NAMES: &[(&str, char)] = &[
+    ("alpha", 'a'),
+    ("bravo", 'b'),
+    ("charlie", 'c'),
+    ("delta", 'd'),
+    ("echo", 'e'),
+    ("foxtrot", 'f'),
+    ("golf", 'g'),
+    ("hotel", 'h'),
+    ("india", 'i'),
+    ("juliet", 'j'),
+    ("kilo", 'k'),
+    ("lima", 'l'),
+    ("mike", 'm'),
+    ("november", 'n'),
+    ("oscar", 'o'),
+    ("papa", 'p'),
+    ("quebec", 'q'),
+    ("romeo", 'r'),
+    ("sierra", 's'),
+    ("tango", 't'),
+    ("uniform", 'u'),
+    ("victor", 'v'),
+    ("whiskey", 'w'),
+    ("xray", 'x'),
+    ("yankee", 'y'),
+    ("zulu", 'z'),
+    ("bee", 'b'),
+    ("see", 'c'),
+    ("sea", 'c'),
+    ("dee", 'd'),
+    ("gee", 'g'),
+    ("jay", 'j'),
+    ("kay", 'k'),
+    ("el", 'l'),
+    ("em", 'm'),
+    ("en", 'n'),
+    ("oh", 'o'),
+    ("pee", 'p'),
+    ("cue", 'q'),
+    ("queue", 'q'),
+    ("are", 'r'),
+    ("ess", 's'),
+    ("tee", 't'),
+    ("tea", 't'),
+    ("you", 'u'),
+    ("vee", 'v'),
+    ("ex", 'x'),
+    ("why", 'y'),
+    ("zee", 'z'),
+    ("zed", 'z'),
+];
+
+/// A whitespace-separated token with its comparison form: lowercase, no
+/// punctuation. Hyphenated tokens are split so "twenty-five" reads as two
+/// number words.
+struct Word<'a> {
+    raw: &'a str,
+    key: String,
+    /// Split from the previous word at a hyphen.
+    hyphenated: bool,
+}
+
+fn split_words(text: &str) -> Vec<Word<'_>> {
+    text.split_whitespace()
+        .flat_map(|token| {
+            let parts: Vec<&str> = token.split('-').filter(|p| !p.is_empty()).collect();
+            if parts.len() > 1 && parts.iter().all(|p| number_word(&key_of(p)).is_some()) {
+                parts
+            } else {
+                vec![token]
+            }
+            .into_iter()
+            .enumerate()
+            .map(|(i, raw)| Word {
+                raw,
+                key: key_of(raw),
+                hyphenated: i > 0,
+            })
+        })
+        .collect()
+}
+
+fn key_of(raw: &str) -> String {
+    raw.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// If `phrase` (space-separated) matches the words at `i`, return its length.
+fn match_phrase(words: &[Word], i: usize, phrase: &str) -> Option<usize> {
+    let parts: Vec<&str> = phrase.split(' ').collect();
+    if i + parts.len() > words.len() {
+        return None;
+    }
+    parts
+        .iter()
+        .zip(&words[i..])
+        .all(|(part, word)| word.key == *part)
+        .then_some(parts.len())
+}
+
+fn match_table<'t, T>(words: &[Word], i: usize, table: &'t [(&str, T)]) -> Option<(usize, &'t T)> {
+    table
+        .iter()
+        .find_map(|(phrase, value)| match_phrase(words, i, phrase).map(|len| (len, value)))
+}
+
+fn match_symbol(words: &[Word], i: usize) -> Option<(usize, &'static str, Spacing)> {
+    CODE_SYMBOLS.iter().find_map(|(phrase, text, spacing)| {
+        match_phrase(words, i, phrase).map(|len| (len, *text, *spacing))
+    })
+}
+
+/// Accumulates output while tracking whether the next word needs a space.
+#[derive(Default)]
+struct Output {
+    text: String,
+    glue: bool,
+}
+
+impl Output {
+    fn word(&mut self, word: &str) {
+        if !self.glue && !self.text.is_empty() && !self.text.ends_with(char::is_whitespace) {
+            self.text.push(' ');
+        }
+        self.text.push_str(word);
+        self.glue = false;
+    }
+
+    fn symbol(&mut self, symbol: &str, spacing: Spacing) {
+        match spacing {
+            Spacing::Attach => {
+                self.text.push_str(symbol);
+                self.glue = true;
+            }
+            Spacing::Trailing => {
+                self.text.push_str(symbol);
+                self.glue = false;
+            }
+            Spacing::Operator => {
+                if !self.text.is_empty() && !self.text.ends_with(char::is_whitespace) {
+                    self.text.push(' ');
+                }
+                self.text.push_str(symbol);
+                self.glue = false;
+            }
+        }
+    }
+}
+
+fn process_code(words: &[Word]) -> String {
+    let mut out = Output::default();
+    let mut i = 0;
+
+    while i < words.len() {
+        if words[i].key == LITERAL && i + 1 < words.len() {
+            out.word(&words[i + 1].key);
+            i += 2;
+            continue;
+        }
+
+        if let Some((len, casing)) = match_table(words, i, CASING_COMMANDS) {
+            i += len;
+            let start = i;
+            while i < words.len()
+                && match_phrase(words, i, END_CASE).is_none()
+                && match_symbol(words, i).is_none()
+                && match_table(words, i, CASING_COMMANDS).is_none()
+            {
+                i += 1;
+            }
+            let parts: Vec<&str> = words[start..i].iter().map(|w| w.key.as_str()).collect();
+            if !parts.is_empty() {
+                out.word(&apply_casing(&parts, *casing));
+            }
+            if let Some(len) = match_phrase(words, i, END_CASE) {
+                i += len;
+            }
+            continue;
+        }
+
+        if let Some(len) = match_phrase(words, i, SPELL) {
+            let (spelled, consumed) = spell(&words[i + len..]);
+            out.word(&spelled);
+            i += len + consumed;
+            continue;
+        }
+
+        if let Some((len, symbol, spacing)) = match_symbol(words, i) {
+            out.symbol(symbol, spacing);
+            i += len;
+            continue;
+        }
+
+        if let Some((value, len)) = parse_number(words, i) {
+            out.word(&value.to_string());
+            i += len;
+            continue;
+        }
+
+        let word = code_word(words[i].raw);
+        if !word.is_empty() {
+            out.word(&word);
+        }
+        i += 1;
+    }
+
+    out.text
+}
+
+/// Strip Whisper's sentence punctuation from a plain word and undo its
+/// sentence-start capitalization, keeping acronyms and mixed case intact.
+fn code_word(raw: &str) -> String {
+    let word = raw.trim_matches(|c: char| ".,;:!?\"".contains(c));
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) if first.is_uppercase() && chars.all(|c| !c.is_uppercase()) => {
+            word.to_lowercase()
+        }
+        _ => word.to_string(),
+    }
+}
+
+/// Consume letters until an end-of-spelling phrase. Returns the spelled word
+/// and how many words were consumed, including the terminator.
+fn spell(words: &[Word]) -> (String, usize) {
+    let mut spelled = String::new();
+    let mut capital = false;
+    let mut i = 0;
+
+    while i < words.len() {
+        if let Some(len) = END_SPELL.iter().find_map(|p| match_phrase(words, i, p)) {
+            return (spelled, i + len);
+        }
+        let key = words[i].key.as_str();
+        if key == "capital" || key == "cap" {
+            capital = true;
+            i += 1;
+            continue;
+        }
+        if let Some((len, symbol, _)) = match_symbol(words, i) {
+            spelled.push_str(symbol);
+            i += len;
+            continue;
+        }
+
+        let letter = if key.chars().count() == 1 {
+            key.chars().next()
+        } else if let Some(digit) = number_word(key).filter(|n| *n < 10) {
+            char::from_digit(digit as u32, 10)
+        } else {
+            LETTER_NAMES
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, c)| *c)
+        };
+        match letter {
+            Some(c) if capital => spelled.extend(c.to_uppercase()),
+            Some(c) => spelled.push(c),
+            None => spelled.push_str(key),
+        }
+        capital = false;
+        i += 1;
+    }
+
+    (spelled, i)
+}
+
+fn apply_casing(parts: &[&str], casing: Casing) -> String {
+    fn capitalize(word: &str) -> String {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+
+    match casing {
+        Casing::Snake => parts.join("_"),
+        Casing::Kebab => parts.join("-"),
+        Casing::Constant => parts.join("_").to_uppercase(),
+        Casing::Pascal => parts.iter().map(|p| capitalize(p)).collect(),
+        Casing::Camel => parts
+            .iter()
+            .enumerate()
+            .map(|(i, p)| if i == 0 { p.to_string() } else { capitalize(p) })
+            .collect(),
+    }
+}
+
+fn process_prose(words: &[Word], config: &DictationConfig) -> String {
+    let mut out = Output::default();
+    let mut capitalize_next = false;
+    // Number words before this index are kept as spoken
+    let mut spoken_until = 0;
+    let mut i = 0;
+
+    while i < words.len() {
+        let punctuation = if config.spoken_punctuation {
+            match_table(words, i, PROSE_PUNCTUATION)
+        } else {
+            None
+        };
+        if let Some((len, mark)) = punctuation {
+            // Whisper often punctuates around spoken punctuation itself
+            let trimmed = out
+                .text
+                .trim_end_matches(|c: char| c == ' ' || ".,;:!?".contains(c))
+                .len();
+            out.text.truncate(trimmed);
+            if mark.starts_with('\n') {
+                out.symbol(mark, Spacing::Attach);
+            } else {
+                out.symbol(mark, Spacing::Trailing);
+            }
+            capitalize_next = matches!(*mark, "." | "?" | "!" | "\n\n");
+            i += len;
+            continue;
+        }
+
+        if config.normalize_numbers && i >= spoken_until {
+            if let Some((value, len)) = parse_number(words, i) {
+                if parse_number(words, i + len).is_some() {
+                    // Years like "twenty twenty-four" and digits read out
+                    // one by one are not a single number
+                    spoken_until = i + words[i..]
+                        .iter()
+                        .take_while(|w| number_word(&w.key).is_some())
+                        .count();
+                } else if len > 1 || value >= 10 {
+                    // Style guides spell out one through nine in running text
+                    out.word(&value.to_string());
+                    out.text
+                        .push_str(trailing_punctuation(words[i + len - 1].raw));
+                    capitalize_next = false;
+                    i += len;
+                    continue;
+                }
+            }
+        }
+
+        if words[i].hyphenated {
+            out.symbol("-", Spacing::Attach);
+        }
+        if capitalize_next {
+            let mut chars = words[i].raw.chars();
+            if let Some(first) = chars.next() {
+                out.word(&first.to_uppercase().chain(chars).collect::<String>());
+            }
+        } else {
+            out.word(words[i].raw);
+        }
+        capitalize_next = false;
+        i += 1;
+    }
+
+    out.text
+}
+
+fn trailing_punctuation(raw: &str) -> &str {
+    let end = raw.trim_end_matches(|c: char| ".,;:!?".contains(c)).len();
+    &raw[end..]
+}
+
+fn number_word(key: &str) -> Option<u64> {
+    let value = match key {
+        "zero" => 0,
+        "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        "thirteen" => 13,
+        "fourteen" => 14,
+        "fifteen" => 15,
+        "sixteen" => 16,
+        "seventeen" => 17,
+        "eighteen" => 18,
+        "nineteen" => 19,
+        "twenty" => 20,
+        "thirty" => 30,
+        "forty" => 40,
+        "fifty" => 50,
+        "sixty" => 60,
+        "seventy" => 70,
+        "eighty" => 80,
+        "ninety" => 90,
+        "hundred" => 100,
+        "thousand" => 1_000,
+        "million" => 1_000_000,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Parse a run of number words starting at `i`, e.g. "two hundred forty
+/// one". Stops at the first word that cannot continue the number, so "one
+/// two" parses as 1 followed by a new number. Returns the value and the
+/// number of words consumed.
+fn parse_number(words: &[Word], i: usize) -> Option<(u64, usize)> {
+    let first = number_word(&words.get(i)?.key)?;
+    if first >= 100 {
+        return None;
+    }
+    if first == 0 {
+        return Some((0, 1));
+    }
+
+    let mut total = 0u64;
+    let mut current = first;
+    let mut last = first;
+    let mut len = 1;
+
+    while let Some(value) = words.get(i + len).and_then(|w| number_word(&w.key)) {
+        let fits = match value {
+            100 => last < 100 && current % 100 != 0,
+            1_000 | 1_000_000 => last != value && last < 1_000_000 && current > 0,
+            _ if value < 10 => ((20..100).contains(&last) && last % 10 == 0) || last >= 100,
+            _ => last >= 100,
+        };
+        if !fits {
+            break;
+        }
+        match value {
+            100 => current *= 100,
+            1_000 | 1_000_000 => {
+                total += current * value;
+                current = 0;
+            }
+            _ => current += value,
+        }
+        last = value;
+        len += 1;
+    }
+
+    Some((total + current, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prose(text: &str) -> String {
+        process(text, &DictationConfig::default())
+    }
+
+    fn code(text: &str) -> String {
+        let config = DictationConfig {
+            mode: DictationMode::Code,
+            ..DictationConfig::default()
+        };
+        process(text, &config)
+    }
+
+    #[test]
+    fn prose_replaces_spoken_punctuation_when_enabled() {
+        let config = DictationConfig {
+            spoken_punctuation: true,
+            ..DictationConfig::default()
+        };
+        let prose = |text: &str| process(text, &config);
+        assert_eq!(
+            prose("Is it ready question mark yes period"),
+            "Is it ready? Yes."
+        );
+        assert_eq!(prose("first comma second"), "first, second");
+        assert_eq!(prose("Done. New paragraph. next"), "Done\n\nNext");
+    }
+
+    #[test]
+    fn prose_keeps_punctuation_words_by_default() {
+        for text in [
+            "The grace period is 30 days.",
+            "Add a new line at the end of the file.",
+            "Use a colon here",
+            "Is this a question mark or a comma?",
+        ] {
+            assert_eq!(prose(text), text);
+        }
+    }
+
+    #[test]
+    fn prose_spells_out_small_numbers() {
+        assert_eq!(prose("add three tests"), "add three tests");
+        assert_eq!(prose("wait twenty-five seconds"), "wait 25 seconds");
+        assert_eq!(
+            prose("about two hundred forty one lines."),
+            "about 241 lines."
+        );
+        assert_eq!(prose("one thousand and nine"), "1000 and nine");
+    }
+
+    #[test]
+    fn prose_keeps_runs_of_numbers_as_spoken() {
+        assert_eq!(prose("Twenty twenty-four"), "Twenty twenty-four");
+        assert_eq!(
+            prose("since nineteen ninety nine, wait ten minutes"),
+            "since nineteen ninety nine, wait 10 minutes"
+        );
+        assert_eq!(prose("dial one two three"), "dial one two three");
+    }
+
+    #[test]
+    fn prose_keeps_number_words_when_disabled() {
+        let config = DictationConfig {
+            normalize_numbers: false,
+            ..DictationConfig::default()
+        };
+        assert_eq!(process("twenty five", &config), "twenty five");
+    }
+
+    #[test]
+    fn code_replaces_spoken_symbols() {
+        assert_eq!(code("Foo dot bar open paren close paren."), "foo.bar()");
+        assert_eq!(code("x plus equals one"), "x += 1");
+        assert_eq!(code("a comma b"), "a, b");
+        assert_eq!(code("std double colon io"), "std::io");
+    }
+
+    #[test]
+    fn code_applies_casing_until_end_case() {
+        assert_eq!(code("snake case user id end case"), "user_id");
+        assert_eq!(code("camel case parse user name"), "parseUserName");
+        assert_eq!(code("Pascal case http client."), "HttpClient");
+        assert_eq!(code("constant case max size equals ten"), "MAX_SIZE = 10");
+        assert_eq!(code("kebab case dry run end case flag"), "dry-run flag");
+    }
+
+    #[test]
+    fn code_spells_letters() {
+        assert_eq!(code("spell capital a bee see end spell"), "Abc");
+        assert_eq!(code("spell x ray one end spell"), "xray1");
+        assert_eq!(code("spell tango oh dee oh stop spelling"), "todo");
+    }
+
+    #[test]
+    fn code_literal_keeps_the_next_word() {
+        assert_eq!(code("literal dot com"), "dot com");
+    }
+
+    #[test]
+    fn code_keeps_acronyms_and_mixed_case() {
+        assert_eq!(code("Use HTTP and JavaScript."), "use HTTP and JavaScript");
+    }
+
+    #[test]
+    fn parses_compound_numbers() {
+        let number = |text: &str| parse_number(&split_words(text), 0);
+        assert_eq!(number("three hundred"), Some((300, 2)));
+        assert_eq!(number("two thousand five hundred six"), Some((2506, 5)));
+        assert_eq!(number("one two"), Some((1, 1)));
+        assert_eq!(number("zero one"), Some((0, 1)));
+        assert_eq!(number("hundred"), None);
+        assert_eq!(number("apple"), None);
+    }
+}
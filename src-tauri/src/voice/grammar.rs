@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+
+use super::dictation::DictationMode;
+
+/// Something a spoken command can make the app do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum VoiceAction {
+    /// Allow the oldest pending permission request.
+    Approve,
+    /// Deny the oldest pending permission request.
+    Deny,
+    /// Interrupt the running agent turn.
+    Stop,
+    /// Stop reading the response aloud, without interrupting the agent.
+    StopSpeaking,
+    /// Skip to the next spoken utterance.
+    SkipSpeech,
+    /// Start a fresh session (handled by the frontend).
+    NewSession,
+    SetPermissionMode {
+        mode: String,
+    },
+    SetDictationMode {
+        mode: DictationMode,
+    },
+    /// Accept a command that is waiting for confirmation.
+    Confirm,
+    /// Discard a command that is waiting for confirmation.
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandRule {
+    /// Utterances that trigger this rule, matched against the whole transcript.
+    pub phrases: Vec<String>,
+    #[serde(flatten)]
+    pub action: VoiceAction,
+    /// Ask for confirmation before running. Meant for destructive actions.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+impl CommandRule {
+    fn new(phrases: &[&str], action: VoiceAction, confirm: bool) -> Self {
+        Self {
+            phrases: phrases.iter().map(|p| p.to_string()).collect(),
+            action,
+            confirm,
+        }
+    }
+}
+
+/// A recognized command and the phrase that triggered it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceCommand {
+    pub phrase: String,
+    #[serde(flatten)]
+    pub action: VoiceAction,
+    #[serde(skip)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommandGrammar {
+    pub enabled: bool,
+    /// If set, commands are only recognized when the transcript starts with
+    /// this word, e.g. "vox approve".
+    pub wake_word: Option<String>,
+    pub rules: Vec<CommandRule>,
+}
+
+impl Default for CommandGrammar {
+    fn default() -> Self {
+        let mode = |mode: &str| VoiceAction::SetPermissionMode {
+            mode: mode.to_string(),
+        };
+        Self {
+            enabled: true,
+            wake_word: None,
+            rules: vec![
+                CommandRule::new(
+                    &["approve", "allow", "allow it", "accept"],
+                    VoiceAction::Approve,
+                    false,
+                ),
+                CommandRule::new(
+                    &["deny", "reject", "decline", "deny it"],
+                    VoiceAction::Deny,
+                    false,
+                ),
+                CommandRule::new(
+                    &["stop", "interrupt", "stop generating"],
+                    VoiceAction::Stop,
+                    false,
+                ),
+                CommandRule::new(
+                    &["stop talking", "stop reading", "be quiet", "quiet"],
+                    VoiceAction::StopSpeaking,
+                    false,
+                ),
+                CommandRule::new(
+                    &["skip", "skip that", "next"],
+                    VoiceAction::SkipSpeech,
+                    false,
+                ),
+                CommandRule::new(
+                    &["new session", "start a new session", "start new session"],
+                    VoiceAction::NewSession,
+                    true,
+                ),
+                CommandRule::new(&["switch to plan mode", "plan mode"], mode("plan"), false),
+                CommandRule::new(
+                    &["switch to default mode", "default mode", "normal mode"],
+                    mode("default"),
+                    false,
+                ),
+                CommandRule::new(
+                    &[
+                        "switch to accept edits mode",
+                        "accept edits mode",
+                        "auto accept edits",
+                    ],
+                    mode("acceptEdits"),
+                    false,
+                ),
+                CommandRule::new(
+                    &["switch to bypass mode", "bypass mode", "bypass permissions"],
+                    mode("bypass"),
+                    true,
+                ),
+                CommandRule::new(
+                    &["switch to code mode", "code mode", "dictate code"],
+                    VoiceAction::SetDictationMode {
+                        mode: DictationMode::Code,
+                    },
+                    false,
+                ),
+                CommandRule::new(
+                    &["switch to prose mode", "prose mode", "dictate prose"],
+                    VoiceAction::SetDictationMode {
+                        mode: DictationMode::Prose,
+                    },
+                    false,
+                ),
+                CommandRule::new(
+                    &["confirm", "yes confirm", "do it"],
+                    VoiceAction::Confirm,
+                    false,
+                ),
+                CommandRule::new(
+                    &["cancel", "never mind", "nevermind"],
+                    VoiceAction::Cancel,
+                    false,
+                ),
+            ],
+        }
+    }
+}
+
+impl CommandGrammar {
+    /// Match a transcript against the grammar. The whole utterance must be a
+    /// command phrase so that ordinary prompts mentioning "stop" or "allow"
+    /// still reach the agent.
+    pub fn recognize(&self, transcript: &str) -> Option<VoiceCommand> {
+        if !self.enabled {
+            return None;
+        }
+
+        let mut utterance = normalize(transcript);
+        if let Some(wake_word) = &self.wake_word {
+            let wake_word = normalize(wake_word);
+            let rest = utterance.strip_prefix(&wake_word)?;
+            if !rest.is_empty() && !rest.starts_with(' ') {
+                return None;
+            }
+            utterance = rest.trim_start().to_string();
+        }
+        if utterance.is_empty() {
+            return None;
+        }
+
+        self.rules.iter().find_map(|rule| {
+            rule.phrases
+                .iter()
+                .find(|phrase| normalize(phrase) == utterance)
+                .map(|phrase| VoiceCommand {
+                    phrase: phrase.clone(),
+                    action: rule.action.clone(),
+                    confirm: rule.confirm,
+                })
+        })
+    }
+}
+
+/// Lowercase, drop punctuation and collapse whitespace.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c.is_whitespace() {
+                c.to_ascii_lowercase()
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(grammar: &CommandGrammar, transcript: &str) -> Option<VoiceAction> {
+        grammar.recognize(transcript).map(|command| command.action)
+    }
+
+    #[test]
+    fn recognizes_default_phrases_ignoring_case_and_punctuation() {
+        let grammar = CommandGrammar::default();
+        assert_eq!(action(&grammar, "Approve."), Some(VoiceAction::Approve));
+        assert_eq!(action(&grammar, "  deny it!"), Some(VoiceAction::Deny));
+        assert_eq!(
+            action(&grammar, "Switch to plan mode"),
+            Some(VoiceAction::SetPermissionMode {
+                mode: "plan".to_string()
+            })
+        );
+        assert_eq!(
+            action(&grammar, "code mode"),
+            Some(VoiceAction::SetDictationMode {
+                mode: DictationMode::Code
+            })
+        );
+    }
+
+    #[test]
+    fn only_whole_utterances_are_commands() {
+        let grammar = CommandGrammar::default();
+        assert_eq!(action(&grammar, "please stop using tabs"), None);
+        assert_eq!(action(&grammar, "allow the user to log in"), None);
+        assert_eq!(action(&grammar, ""), None);
+    }
+
+    #[test]
+    fn destructive_commands_ask_for_confirmation() {
+        let grammar = CommandGrammar::default();
+        let command = grammar.recognize("New session").unwrap();
+        assert_eq!(command.action, VoiceAction::NewSession);
+        assert_eq!(command.phrase, "new session");
+        assert!(command.confirm);
+        assert!(grammar.recognize("bypass mode").unwrap().confirm);
+        assert!(!grammar.recognize("approve").unwrap().confirm);
+    }
+
+    #[test]
+    fn wake_word_is_required_when_set() {
+        let grammar = CommandGrammar {
+            wake_word: Some("Vox".to_string()),
+            ..CommandGrammar::default()
+        };
+        assert_eq!(action(&grammar, "Vox, approve"), Some(VoiceAction::Approve));
+        assert_eq!(action(&grammar, "approve"), None);
+        assert_eq!(action(&grammar, "voxapprove"), None);
+        assert_eq!(action(&grammar, "vox"), None);
+    }
+
+    #[test]
+    fn disabled_grammar_recognizes_nothing() {
+        let grammar = CommandGrammar {
+            enabled: false,
+            ..CommandGrammar::default()
+        };
+        assert_eq!(action(&grammar, "approve"), None);
+    }
+
+    #[test]
+    fn rules_round_trip_through_settings_json() {
+        let grammar = CommandGrammar::default();
+        let json = serde_json::to_string(&grammar).unwrap();
+        let parsed: CommandGrammar = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, grammar);
+    }
+}
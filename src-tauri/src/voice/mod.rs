@@ -0,0 +1,188 @@
+pub mod cascade;
+pub mod corrections;
+pub mod dictation;
+pub mod grammar;
+pub mod permission;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::info;
+
+use crate::audio::earcons::{self, Earcon};
+use crate::sidecar::protocol::ToSidecar;
+use crate::state::AppState;
+use dictation::DictationMode;
+use grammar::{VoiceAction, VoiceCommand};
+
+/// A destructive command held back until the user confirms it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingVoiceCommand {
+    pub id: String,
+    #[serde(flatten)]
+    pub command: VoiceCommand,
+}
+
+/// Stop the current recording, transcribe it and route the transcript, then
+/// start the refinement pass if the cascade is enabled.
+pub fn finish_recording(state: &AppState, app_handle: &AppHandle) -> Result<String> {
+    let (session_id, text, refine_job) = {
+        let mut audio = state.audio.lock().unwrap();
+        let text = audio.stop_recording(app_handle.clone())?;
+        (
+            audio.recording_session().to_string(),
+            text,
+            audio.take_refine_job(),
+        )
+    };
+    let text = handle_transcript(state, app_handle, &session_id, text)?;
+
+    if let (false, Some(job)) = (text.is_empty(), refine_job) {
+        cascade::spawn_refinement(app_handle.clone(), job, text.clone());
+    }
+    Ok(text)
+}
+
+/// Route a finished transcript. If it matches the command grammar it is run
+/// as a command and an empty string is returned; otherwise it is emitted as a
+/// `transcription` event and returned for the input box.
+pub fn handle_transcript(
+    state: &AppState,
+    app_handle: &AppHandle,
+    session_id: &str,
+    text: String,
+) -> Result<String> {
+    if text.is_empty() {
+        return Ok(text);
+    }
+
+    let command = state
+        .settings
+        .lock()
+        .unwrap()
+        .voice_commands
+        .recognize(&text);
+    if let Some(command) = command {
+        info!("Voice command: {:?}", command);
+        run_command(state, app_handle, command)?;
+        return Ok(String::new());
+    }
+
+    let text = postprocess(state, &text);
+    state
+        .corrections
+        .lock()
+        .unwrap()
+        .set_last_transcript(session_id, &text);
+    let _ = app_handle.emit("transcription", &text);
+    earcons::cue(app_handle, Earcon::TranscriptionReady);
+    Ok(text)
+}
+
+/// Apply dictation rewriting and learned corrections to raw engine output.
+pub fn postprocess(state: &AppState, text: &str) -> String {
+    let dictation = state.settings.lock().unwrap().dictation.clone();
+    let text = dictation::process(text, &dictation);
+    state.corrections.lock().unwrap().apply(&text)
+}
+
+fn run_command(state: &AppState, app_handle: &AppHandle, command: VoiceCommand) -> Result<()> {
+    match command.action {
+        VoiceAction::Confirm => {
+            let pending = state.pending_voice_command.lock().unwrap().take();
+            match pending {
+                Some(pending) => execute(state, app_handle, pending.command),
+                None => {
+                    info!("No voice command waiting for confirmation");
+                    Ok(())
+                }
+            }
+        }
+        VoiceAction::Cancel => {
+            if let Some(pending) = state.pending_voice_command.lock().unwrap().take() {
+                let _ = app_handle.emit("voice-command-cancelled", &pending.id);
+            }
+            Ok(())
+        }
+        _ if command.confirm => {
+            let pending = PendingVoiceCommand {
+                id: uuid::Uuid::new_v4().to_string(),
+                command,
+            };
+            let _ = app_handle.emit("voice-command-confirm", &pending);
+            *state.pending_voice_command.lock().unwrap() = Some(pending);
+            Ok(())
+        }
+        _ => execute(state, app_handle, command),
+    }
+}
+
+/// Resolve a command that was held for confirmation, e.g. from a UI prompt.
+pub fn confirm_command(
+    state: &AppState,
+    app_handle: &AppHandle,
+    id: &str,
+    confirmed: bool,
+) -> Result<()> {
+    let pending = {
+        let mut guard = state.pending_voice_command.lock().unwrap();
+        match guard.as_ref() {
+            Some(pending) if pending.id == id => guard.take().unwrap(),
+            _ => anyhow::bail!("No voice command {} waiting for confirmation", id),
+        }
+    };
+
+    if confirmed {
+        execute(state, app_handle, pending.command)
+    } else {
+        let _ = app_handle.emit("voice-command-cancelled", &pending.id);
+        Ok(())
+    }
+}
+
+/// Run a command against the active session.
+fn execute(state: &AppState, app_handle: &AppHandle, command: VoiceCommand) -> Result<()> {
+    let sidecar = state.sessions.lock().unwrap().active()?;
+    match &command.action {
+        VoiceAction::Approve | VoiceAction::Deny => {
+            let pending = sidecar
+                .oldest_pending_permission()
+                .context("No pending permission request")?;
+            let decision = if command.action == VoiceAction::Approve {
+                "allow"
+            } else {
+                "deny"
+            };
+            sidecar.send(&ToSidecar::RespondPermission {
+                request_id: pending.request_id,
+                decision: decision.to_string(),
+            })?;
+        }
+        VoiceAction::Stop => {
+            state.speaker.lock().unwrap().stop();
+            sidecar.send(&ToSidecar::Interrupt)?;
+        }
+        VoiceAction::StopSpeaking => state.speaker.lock().unwrap().stop(),
+        VoiceAction::SkipSpeech => state.speaker.lock().unwrap().skip(),
+        VoiceAction::SetPermissionMode { mode } => {
+            sidecar.send(&ToSidecar::SetPermissionMode { mode: mode.clone() })?;
+        }
+        VoiceAction::SetDictationMode { mode } => set_dictation_mode(state, *mode)?,
+        // Opened by the frontend, which owns session names and directories,
+        // on the event below
+        VoiceAction::NewSession => {}
+        VoiceAction::Confirm | VoiceAction::Cancel => {}
+    }
+
+    let _ = app_handle.emit("voice-command", &command);
+    Ok(())
+}
+
+pub fn set_dictation_mode(state: &AppState, mode: DictationMode) -> Result<()> {
+    let mut settings = state.settings.lock().unwrap();
+    settings.dictation.mode = mode;
+    settings.save()?;
+    info!("Dictation mode set to {:?}", mode);
+    Ok(())
+}
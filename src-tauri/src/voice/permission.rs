@@ -0,0 +1,226 @@
+use std::path::Path;
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info};
+
+use crate::sidecar::manager::{PendingPermission, SidecarManager};
+use crate::sidecar::protocol::ToSidecar;
+use crate::state::AppState;
+
+/// Longest the prompt waits for speech already playing to finish.
+const SPEECH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Words of a shell command read out before it is cut off.
+const MAX_COMMAND_WORDS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpokenPermissionConfig {
+    /// Read permission requests aloud and listen for a spoken answer.
+    pub enabled: bool,
+    /// How long to listen for the answer after the prompt.
+    pub listen_secs: f32,
+}
+
+impl Default for SpokenPermissionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_secs: 4.0,
+        }
+    }
+}
+
+/// Payload of the `permission-voice-answer` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceAnswer {
+    pub request_id: String,
+    pub decision: Option<String>,
+    pub transcript: String,
+}
+
+/// One sentence describing a permission request, e.g. "Bash wants to run
+/// cargo test in voxcode".
+pub fn summary(tool_name: &str, input: &serde_json::Value, cwd: Option<&str>) -> String {
+    let file = |key: &str| {
+        input[key]
+            .as_str()
+            .map(|p| {
+                Path::new(p)
+                    .file_name()
+                    .map_or(p.to_string(), |n| n.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "a file".to_string())
+    };
+
+    let action = match tool_name {
+        "Bash" => match input["command"].as_str() {
+            Some(command) => {
+                let words: Vec<&str> = command.split_whitespace().collect();
+                let mut spoken = words[..words.len().min(MAX_COMMAND_WORDS)].join(" ");
+                if words.len() > MAX_COMMAND_WORDS {
+                    spoken.push_str(" and so on");
+                }
+                format!("wants to run {}", spoken)
+            }
+            None => "wants to run a command".to_string(),
+        },
+        "Edit" | "MultiEdit" => format!("wants to edit {}", file("file_path")),
+        "Write" => format!("wants to write {}", file("file_path")),
+        "Read" => format!("wants to read {}", file("file_path")),
+        "NotebookEdit" => format!("wants to edit {}", file("notebook_path")),
+        "WebFetch" => match input["url"].as_str() {
+            Some(url) => format!("wants to fetch {}", host(url)),
+            None => "wants to fetch a web page".to_string(),
+        },
+        "WebSearch" => match input["query"].as_str() {
+            Some(query) => format!("wants to search the web for {}", query),
+            None => "wants to search the web".to_string(),
+        },
+        _ => "wants permission".to_string(),
+    };
+
+    let project = cwd
+        .and_then(|cwd| Path::new(cwd).file_name())
+        .map(|name| format!(" in {}", name.to_string_lossy()))
+        .unwrap_or_default();
+    format!("{} {}{}", tool_name, action, project)
+}
+
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+/// Map a spoken answer to `allow`/`deny`, or `None` if it was neither.
+pub fn parse_answer(transcript: &str) -> Option<&'static str> {
+    let normalized = transcript
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '\'')
+        .collect::<String>()
+        .to_lowercase();
+    let words: Vec<&str> = normalized.split_whitespace().collect();
+
+    const ALLOW: &[&str] = &[
+        "yes", "yeah", "yep", "allow", "approve", "sure", "ok", "okay",
+    ];
+    const DENY: &[&str] = &["no", "nope", "deny", "reject", "don't", "stop", "cancel"];
+
+    match words.as_slice() {
+        ["go", "ahead", ..] | ["do", "it", ..] => Some("allow"),
+        [first, ..] if ALLOW.contains(first) => Some("allow"),
+        [first, ..] if DENY.contains(first) => Some("deny"),
+        _ => None,
+    }
+}
+
+/// Read a new permission request from `sidecar`'s session aloud and listen
+/// for the answer on a background thread.
+pub fn spawn_prompt(
+    app_handle: AppHandle,
+    sidecar: Weak<SidecarManager>,
+    request: PendingPermission,
+) {
+    thread::spawn(move || {
+        let state = app_handle.state::<AppState>();
+        let config = state.settings.lock().unwrap().spoken_permissions.clone();
+        if !config.enabled {
+            return;
+        }
+
+        let Some(cwd) = sidecar.upgrade().map(|s| s.cwd()) else {
+            return;
+        };
+        let text = summary(&request.tool_name, &request.input, cwd.as_deref());
+        info!("Spoken permission prompt: {}", text);
+        {
+            let speaker = state.speaker.lock().unwrap();
+            // The prompt is more urgent than the rest of the response
+            speaker.stop();
+            speaker.speak(&text);
+        }
+
+        let deadline = Instant::now() + SPEECH_TIMEOUT;
+        while state.speaker.lock().unwrap().is_speaking() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        if !is_pending(&sidecar, &request.request_id) {
+            return;
+        }
+
+        if let Err(e) = listen(&app_handle, &state, &config, &sidecar, &request) {
+            error!("Spoken permission answer failed: {}", e);
+        }
+    });
+}
+
+/// False once answered elsewhere or the session is closed.
+fn is_pending(sidecar: &Weak<SidecarManager>, request_id: &str) -> bool {
+    sidecar
+        .upgrade()
+        .is_some_and(|s| s.is_permission_pending(request_id))
+}
+
+fn listen(
+    app_handle: &AppHandle,
+    state: &AppState,
+    config: &SpokenPermissionConfig,
+    sidecar: &Weak<SidecarManager>,
+    request: &PendingPermission,
+) -> anyhow::Result<()> {
+    {
+        let mut audio = state.audio.lock().unwrap();
+        if audio.is_recording() {
+            // The user is already talking, e.g. after barging in; their
+            // transcript goes through the voice command grammar instead
+            return Ok(());
+        }
+        let Some(sidecar) = sidecar.upgrade() else {
+            return Ok(());
+        };
+        audio.start_recording(app_handle.clone(), sidecar.session_id())?;
+    }
+
+    thread::sleep(Duration::from_secs_f32(config.listen_secs.max(0.5)));
+    let transcript = state
+        .audio
+        .lock()
+        .unwrap()
+        .stop_recording(app_handle.clone())?;
+    let _ = app_handle.emit("recording-stopped", ());
+
+    let decision = parse_answer(&transcript);
+    info!(
+        "Spoken permission answer {:?} -> {:?}",
+        transcript, decision
+    );
+    // The session was closed while we were listening
+    let Some(sidecar) = sidecar.upgrade() else {
+        return Ok(());
+    };
+    if let Some(decision) = decision {
+        // Answered in the UI while we were listening
+        if !sidecar.is_permission_pending(&request.request_id) {
+            return Ok(());
+        }
+        sidecar.send(&ToSidecar::RespondPermission {
+            request_id: request.request_id.clone(),
+            decision: decision.to_string(),
+        })?;
+    }
+
+    sidecar.emit(
+        app_handle,
+        "permission-voice-answer",
+        VoiceAnswer {
+            request_id: request.request_id.clone(),
+            decision: decision.map(str::to_string),
+            transcript,
+        },
+    );
+    Ok(())
+}
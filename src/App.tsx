@@ -2,6 +2,7 @@ import { useRef, useState, useEffect, useCallback } from "react";
 import { ChatContainer } from "./components/chat/ChatContainer";
 import { InputBar } from "./components/chat/InputBar";
 import { ApprovalBanner } from "./components/approval/ApprovalBanner";
+import { VoiceCommandConfirm } from "./components/approval/VoiceCommandConfirm";
 import { Header } from "./components/layout/Header";
 import { StatusBar } from "./components/layout/StatusBar";
 import { MicButton } from "./components/audio/MicButton";
@@ -14,11 +15,34 @@ import { useChat } from "./hooks/useChat";
 import { useAudio } from "./hooks/useAudio";
 import { useKeyboardShortcuts } from "./hooks/useKeyboardShortcuts";
 import { useSettingsStore } from "./stores/settingsStore";
-import type { Session } from "./stores/sessionStore";
+import { useSessionStore, type Session } from "./stores/sessionStore";
+import type { PermissionMode } from "./lib/types";
 import * as tauri from "./lib/tauri";
 
+const SIDECAR_LOG_LINES = 200;
+
+/** What the status bar says about a sidecar that is not running. */
+function describeSidecarStatus(status: tauri.SidecarStatus): string | null {
+  const exit =
+    status.signal !== null
+      ? `signal ${status.signal}`
+      : `exit code ${status.exitCode ?? "unknown"}`;
+  const lastLine = status.stderr[status.stderr.length - 1];
+  switch (status.state) {
+    case "restarting": {
+      const seconds = Math.ceil((status.restartInMs ?? 0) / 1000);
+      return `Sidecar crashed (${exit}); restarting in ${seconds}s, attempt ${status.attempt}`;
+    }
+    case "failed":
+      return `Sidecar keeps crashing (${exit}); gave up restarting${lastLine ? `: ${lastLine}` : ""}`;
+    default:
+      return null;
+  }
+}
+
 export default function App() {
   const {
+    sessionId,
     messages,
     isLoading,
     streamingContent,
@@ -40,19 +64,57 @@ export default function App() {
     loadModel,
   } = useAudio();
 
-  const { setCwd } = useSettingsStore();
+  const { setCwd, setPermissionMode } = useSettingsStore();
 
   const [isConnected, setIsConnected] = useState(false);
+  const [sidecarProblem, setSidecarProblem] = useState<string | null>(null);
+  // No session can start until the sidecar settings are fixed
+  const [launchProblem, setLaunchProblem] = useState<string | null>(null);
   const [sidebarOpen, setSidebarOpen] = useState(false);
   const [commandPaletteOpen, setCommandPaletteOpen] = useState(false);
   const [settingsOpen, setSettingsOpen] = useState(false);
+  const [pendingVoiceCommand, setPendingVoiceCommand] =
+    useState<tauri.PendingVoiceCommand | null>(null);
   const inputRef = useRef<HTMLTextAreaElement>(null);
 
-  // Track sidecar connection
+  // Track the connection of the shown session's sidecar
+  const sessionIdRef = useRef(sessionId);
   useEffect(() => {
-    tauri.onSessionReady(() => setIsConnected(true));
-    tauri.onSidecarExited(() => setIsConnected(false));
-    tauri.isSidecarRunning().then(setIsConnected).catch(() => {});
+    sessionIdRef.current = sessionId;
+    setSidecarProblem(null);
+    tauri.isSidecarRunning(sessionId).then(setIsConnected).catch(() => {});
+  }, [sessionId]);
+
+  useEffect(() => {
+    // The startup check may have failed before we were listening
+    tauri
+      .checkSidecarLaunch()
+      .then(() => setLaunchProblem(null))
+      .catch((failure: tauri.LaunchFailure) =>
+        setLaunchProblem(failure.message)
+      );
+    tauri.onSidecarLaunchError((failure) => setLaunchProblem(failure.message));
+
+    const isShown = (id: string) => id === sessionIdRef.current;
+    tauri.onSessionReady((_, id) => {
+      if (isShown(id)) setIsConnected(true);
+    });
+    tauri.onSidecarExited((id) => {
+      if (isShown(id)) setIsConnected(false);
+    });
+    tauri.onSidecarIncompatible((message, id) => {
+      if (isShown(id)) setSidecarProblem(message);
+    });
+    tauri.onSidecarStatus((status, id) => {
+      if (!isShown(id)) return;
+      setIsConnected(status.state === "running");
+      if (status.state === "running") {
+        setSidecarProblem(null);
+        setLaunchProblem(null);
+      } else {
+        setSidecarProblem(describeSidecarStatus(status));
+      }
+    });
   }, []);
 
   // Handle transcription -> input bar
@@ -69,13 +131,107 @@ export default function App() {
     }
   }, []);
 
+  // Swap a draft transcript for its refined version, unless the user has
+  // already edited that part of the input
+  useEffect(() => {
+    const unlisten = tauri.onTranscriptionRefined(({ draft, text }) => {
+      const input = inputRef.current;
+      if (!input || !input.value.endsWith(draft)) return;
+      const nativeInputValueSetter = Object.getOwnPropertyDescriptor(
+        window.HTMLTextAreaElement.prototype,
+        "value"
+      )?.set;
+      const newValue = input.value.slice(0, input.value.length - draft.length) + text;
+      nativeInputValueSetter?.call(input, newValue);
+      input.dispatchEvent(new Event("input", { bubbles: true }));
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Spawn the shown session's sidecar again after a failed start or a
+  // crash loop
+  const handleReconnect = useCallback(() => {
+    const cwd = useSettingsStore.getState().cwd;
+    setSidecarProblem(null);
+    tauri
+      .openSession(sessionIdRef.current, cwd || undefined)
+      .then((info) => setIsConnected(info.running))
+      .catch((err) => setSidecarProblem(`Failed to reconnect: ${err}`));
+  }, []);
+
   // Session select
   const handleSessionSelect = useCallback(
-    (_session: Session) => {
-      setCwd(_session.cwd);
+    (session: Session) => {
+      setCwd(session.cwd);
       setSidebarOpen(false);
+      // Voice commands follow the shown session, which keeps its own mode
+      tauri
+        .setActiveSession(session.id)
+        .then(() => tauri.getSession(session.id))
+        .then((info) => setPermissionMode(info.permissionMode as PermissionMode))
+        .catch((err) => console.error("Failed to switch session:", err));
     },
-    [setCwd]
+    [setCwd, setPermissionMode]
+  );
+
+  // "New session" by voice: like the sidebar's button, but in the shown
+  // session's directory and without prompts
+  const openVoiceSession = useCallback(async () => {
+    const { sessions, createSession, setSessionProfile } =
+      useSessionStore.getState();
+    const cwd = useSettingsStore.getState().cwd;
+    const name = `Session ${sessions.length + 1}`;
+    const id = createSession(name, cwd, null);
+    let profile: string | null = null;
+    try {
+      profile = (await tauri.openSession(id, cwd || undefined)).profile;
+      setSessionProfile(id, profile);
+    } catch (err) {
+      console.error("Failed to open session:", err);
+    }
+    handleSessionSelect({
+      id,
+      name,
+      cwd,
+      profile,
+      createdAt: Date.now(),
+      lastActiveAt: Date.now(),
+      messageCount: 0,
+    });
+  }, [handleSessionSelect]);
+
+  // Voice commands the backend leaves to the UI, and confirmation of
+  // destructive ones
+  useEffect(() => {
+    const unlisteners = [
+      tauri.onVoiceCommand((command) => {
+        setPendingVoiceCommand(null);
+        if (command.action === "newSession") {
+          openVoiceSession();
+        } else if (command.action === "setPermissionMode" && command.mode) {
+          setPermissionMode(command.mode as PermissionMode);
+        }
+      }),
+      tauri.onVoiceCommandConfirm(setPendingVoiceCommand),
+      tauri.onVoiceCommandCancelled((id) =>
+        setPendingVoiceCommand((pending) => (pending?.id === id ? null : pending))
+      ),
+    ];
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
+  }, [openVoiceSession, setPermissionMode]);
+
+  const handleVoiceCommandResponse = useCallback(
+    (id: string, confirmed: boolean) => {
+      setPendingVoiceCommand(null);
+      tauri
+        .confirmVoiceCommand(id, confirmed)
+        .catch((err) => console.error("Failed to answer voice command:", err));
+    },
+    []
   );
 
   // Command palette
@@ -97,11 +253,18 @@ export default function App() {
         case "usage":
           send("/usage");
           break;
+        case "sidecar-log":
+          // For bug reports
+          tauri
+            .readSidecarLog(sessionId, SIDECAR_LOG_LINES)
+            .then((lines) => navigator.clipboard.writeText(lines.join("\n")))
+            .catch((err) => console.error("Failed to copy sidecar log:", err));
+          break;
         default:
           break;
       }
     },
-    [clearMessages, send]
+    [clearMessages, send, sessionId]
   );
 
   // Keyboard shortcuts
@@ -163,6 +326,10 @@ export default function App() {
           onApprove={approvePermission}
           onDeny={denyPermission}
         />
+        <VoiceCommandConfirm
+          command={pendingVoiceCommand}
+          onRespond={handleVoiceCommandResponse}
+        />
         <TranscriptionPreview text={transcription} isRecording={isRecording} />
         <InputBar
           onSend={send}
@@ -184,6 +351,8 @@ export default function App() {
         />
         <StatusBar
           isConnected={isConnected}
+          sidecarProblem={launchProblem ?? sidecarProblem}
+          onReconnect={launchProblem ? undefined : handleReconnect}
           isLoading={isLoading}
           pendingApprovals={pendingPermissions.length}
         />
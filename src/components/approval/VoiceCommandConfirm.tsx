@@ -0,0 +1,39 @@
+import { Mic, Check, X } from "lucide-react";
+import type { PendingVoiceCommand } from "../../lib/tauri";
+
+interface VoiceCommandConfirmProps {
+  command: PendingVoiceCommand | null;
+  onRespond: (id: string, confirmed: boolean) => void;
+}
+
+export function VoiceCommandConfirm({ command, onRespond }: VoiceCommandConfirmProps) {
+  if (!command) return null;
+
+  return (
+    <div className="mx-4 my-2 border border-amber-700/50 bg-amber-950/20 rounded-xl overflow-hidden animate-in slide-in-from-bottom-2">
+      <div className="flex items-center gap-2 px-4 py-3">
+        <Mic size={16} className="text-amber-400" />
+        <span className="text-sm font-medium text-amber-200">
+          Run voice command "{command.phrase}"?
+        </span>
+        <span className="text-xs text-zinc-500 ml-auto">
+          Say "confirm" or "cancel"
+        </span>
+        <button
+          onClick={() => onRespond(command.id, true)}
+          className="flex items-center gap-1.5 px-3 py-1 bg-green-600 hover:bg-green-500 text-white rounded-lg text-sm font-medium transition-colors"
+        >
+          <Check size={14} />
+          Confirm
+        </button>
+        <button
+          onClick={() => onRespond(command.id, false)}
+          className="flex items-center gap-1.5 px-3 py-1 bg-zinc-700 hover:bg-zinc-600 text-white rounded-lg text-sm font-medium transition-colors"
+        >
+          <X size={14} />
+          Cancel
+        </button>
+      </div>
+    </div>
+  );
+}
@@ -7,6 +7,7 @@ import {
   BarChart2,
   Download,
   Settings,
+  FileText,
 } from "lucide-react";
 
 interface CommandPaletteProps {
@@ -58,6 +59,12 @@ const COMMANDS = [
     icon: Settings,
     shortcut: "/settings",
   },
+  {
+    id: "sidecar-log",
+    label: "Copy sidecar log",
+    icon: FileText,
+    shortcut: "/log",
+  },
 ];
 
 export function CommandPalette({
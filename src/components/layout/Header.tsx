@@ -1,5 +1,7 @@
-import { Zap, FolderOpen, Trash2, Command } from "lucide-react";
+import { useEffect, useState } from "react";
+import { Zap, FolderOpen, Trash2, Command, Languages } from "lucide-react";
 import { useSettingsStore } from "../../stores/settingsStore";
+import { useSessionStore, DEFAULT_SESSION_ID } from "../../stores/sessionStore";
 import type { PermissionMode } from "../../lib/types";
 import * as tauri from "../../lib/tauri";
 
@@ -17,10 +19,30 @@ const PERMISSION_MODES: { value: PermissionMode; label: string }[] = [
 export function Header({ onClear }: HeaderProps) {
   const { permissionMode, cwd, setPermissionMode, setCwd } =
     useSettingsStore();
+  const sessionId =
+    useSessionStore((state) => state.activeSessionId) ?? DEFAULT_SESSION_ID;
+  const [transcribe, setTranscribe] = useState<tauri.TranscribeOptions | null>(
+    null
+  );
+
+  useEffect(() => {
+    setTranscribe(null);
+    tauri
+      .getTranscribeOptions(sessionId)
+      .then(setTranscribe)
+      .catch(() => {});
+  }, [sessionId]);
+
+  const handleTranslateToggle = async () => {
+    if (!transcribe) return;
+    const options = { ...transcribe, translate: !transcribe.translate };
+    await tauri.setTranscribeOptions(sessionId, options);
+    setTranscribe(options);
+  };
 
   const handleModeChange = async (mode: PermissionMode) => {
     setPermissionMode(mode);
-    await tauri.setPermissionMode(mode);
+    await tauri.setPermissionMode(sessionId, mode);
   };
 
   const handleSetCwd = () => {
@@ -52,6 +74,19 @@ export function Header({ onClear }: HeaderProps) {
           <FolderOpen size={14} />
           {cwd ? "Change dir" : "Set dir"}
         </button>
+        <button
+          onClick={handleTranslateToggle}
+          disabled={!transcribe}
+          className={`flex items-center gap-1.5 px-2.5 py-1 text-xs rounded transition-colors ${
+            transcribe?.translate
+              ? "text-violet-300 bg-violet-500/15 hover:bg-violet-500/25"
+              : "text-zinc-400 hover:text-zinc-200 hover:bg-zinc-800"
+          }`}
+          title="Translate speech in this session to English"
+        >
+          <Languages size={14} />
+          Translate
+        </button>
         <select
           value={permissionMode}
           onChange={(e) =>
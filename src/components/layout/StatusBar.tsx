@@ -2,12 +2,16 @@ import { Circle } from "lucide-react";
 
 interface StatusBarProps {
   isConnected: boolean;
+  sidecarProblem?: string | null;
+  onReconnect?: () => void;
   isLoading: boolean;
   pendingApprovals: number;
 }
 
 export function StatusBar({
   isConnected,
+  sidecarProblem,
+  onReconnect,
   isLoading,
   pendingApprovals,
 }: StatusBarProps) {
@@ -25,6 +29,19 @@ export function StatusBar({
           />
           {isConnected ? "Connected" : "Disconnected"}
         </div>
+        {!isConnected && onReconnect && (
+          <button
+            onClick={onReconnect}
+            className="text-violet-400 hover:text-violet-300 transition-colors"
+          >
+            Reconnect
+          </button>
+        )}
+        {sidecarProblem && (
+          <span className="text-red-400" title={sidecarProblem}>
+            {sidecarProblem}
+          </span>
+        )}
         {isLoading && (
           <span className="text-violet-400">Processing...</span>
         )}
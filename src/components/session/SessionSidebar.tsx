@@ -1,6 +1,8 @@
 import { useState } from "react";
-import { Plus, X, MessageSquare, FolderOpen, Pencil, Trash2 } from "lucide-react";
+import { Plus, X, MessageSquare, FolderOpen, Pencil, Trash2, KeyRound } from "lucide-react";
 import { useSessionStore, type Session } from "../../stores/sessionStore";
+import { useChatStore } from "../../stores/chatStore";
+import * as tauri from "../../lib/tauri";
 
 interface SessionSidebarProps {
   isOpen: boolean;
@@ -20,22 +22,76 @@ export function SessionSidebar({
     renameSession,
     removeSession,
     setActiveSession,
+    setSessionProfile,
   } = useSessionStore();
+  const removeChat = useChatStore((state) => state.removeChat);
 
   const [editingId, setEditingId] = useState<string | null>(null);
   const [editName, setEditName] = useState("");
 
   if (!isOpen) return null;
 
-  const handleNew = () => {
+  // Ask for one of the stored environment profiles; undefined if cancelled
+  const promptProfile = async (
+    current: string | null
+  ): Promise<string | null | undefined> => {
+    const profiles = await tauri.listEnvProfiles().catch(() => []);
+    if (profiles.length === 0) return null;
+    const names = profiles.map((p) => p.name);
+    const answer = prompt(
+      `Environment profile (${names.join(", ")}), empty for none:`,
+      current ?? ""
+    );
+    if (answer === null) return undefined;
+    const name = answer.trim();
+    if (!name) return null;
+    if (!names.includes(name)) {
+      alert(`No environment profile "${name}"`);
+      return undefined;
+    }
+    return name;
+  };
+
+  const handleNew = async () => {
     const cwd = prompt("Working directory for new session:", "/home/peter");
     if (!cwd) return;
     const name = prompt("Session name:", `Session ${sessions.length + 1}`) || `Session ${sessions.length + 1}`;
-    const id = createSession(name, cwd);
-    const session = { id, name, cwd, createdAt: Date.now(), lastActiveAt: Date.now(), messageCount: 0 };
+    const chosen = await promptProfile(await tauri.getDefaultEnvProfile().catch(() => null));
+    if (chosen === undefined) return;
+    let profile = chosen;
+    const id = createSession(name, cwd, profile);
+    try {
+      // Each session gets its own sidecar
+      const info = await tauri.openSession(id, cwd, profile ?? undefined);
+      // No choice means the default profile
+      profile = info.profile;
+      setSessionProfile(id, profile);
+    } catch (err) {
+      console.error("Failed to open session:", err);
+    }
+    const session = { id, name, cwd, profile, createdAt: Date.now(), lastActiveAt: Date.now(), messageCount: 0 };
     onSessionSelect(session);
   };
 
+  const handleProfile = async (session: Session) => {
+    const profile = await promptProfile(session.profile);
+    if (profile === undefined || profile === session.profile) return;
+    try {
+      await tauri.setSessionProfile(session.id, profile);
+      setSessionProfile(session.id, profile);
+    } catch (err) {
+      alert(`Failed to switch profile: ${err}`);
+    }
+  };
+
+  const handleRemove = (id: string) => {
+    tauri.closeSession(id).catch((err) => {
+      console.error("Failed to close session:", err);
+    });
+    removeSession(id);
+    removeChat(id);
+  };
+
   const handleRename = (id: string) => {
     if (editName.trim()) {
       renameSession(id, editName.trim());
@@ -104,10 +160,26 @@ export function SessionSidebar({
                     <FolderOpen size={10} />
                     {session.cwd}
                   </div>
+                  {session.profile && (
+                    <div className="text-xs text-zinc-600 truncate flex items-center gap-1">
+                      <KeyRound size={10} />
+                      {session.profile}
+                    </div>
+                  )}
                 </>
               )}
             </div>
             <div className="hidden group-hover:flex gap-0.5">
+              <button
+                onClick={(e) => {
+                  e.stopPropagation();
+                  handleProfile(session);
+                }}
+                className="p-0.5 text-zinc-600 hover:text-zinc-300 rounded"
+                title="Environment profile"
+              >
+                <KeyRound size={12} />
+              </button>
               <button
                 onClick={(e) => {
                   e.stopPropagation();
@@ -121,7 +193,7 @@ export function SessionSidebar({
               <button
                 onClick={(e) => {
                   e.stopPropagation();
-                  removeSession(session.id);
+                  handleRemove(session.id);
                 }}
                 className="p-0.5 text-zinc-600 hover:text-red-400 rounded"
               >
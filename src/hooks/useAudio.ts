@@ -22,6 +22,16 @@ export function useAudio() {
       setTranscription(e.payload);
     }).then((fn) => unlisteners.push(fn));
 
+    // Talking over spoken output starts a recording that ends on silence
+    listen("barge-in", () => {
+      setIsRecording(true);
+      setTranscription("");
+    }).then((fn) => unlisteners.push(fn));
+
+    listen("recording-stopped", () => {
+      setIsRecording(false);
+    }).then((fn) => unlisteners.push(fn));
+
     return () => {
       unlisteners.forEach((fn) => fn());
     };
@@ -1,87 +1,114 @@
 import { useEffect, useCallback } from "react";
-import { useChatStore } from "../stores/chatStore";
+import { useChatStore, EMPTY_CHAT } from "../stores/chatStore";
+import { useSessionStore, DEFAULT_SESSION_ID } from "../stores/sessionStore";
 import { useSettingsStore } from "../stores/settingsStore";
 import * as tauri from "../lib/tauri";
 
 export function useChat() {
   const {
-    messages,
-    isLoading,
-    streamingContent,
-    pendingPermissions,
+    chats,
     addUserMessage,
     handleSdkMessage,
     handleStreamingText,
     handleTurnComplete,
     addPermissionRequest,
     removePermissionRequest,
+    setLoading,
     clearMessages,
   } = useChatStore();
 
+  const sessionId =
+    useSessionStore((state) => state.activeSessionId) ?? DEFAULT_SESSION_ID;
+  const { messages, isLoading, streamingContent, pendingPermissions } =
+    chats[sessionId] ?? EMPTY_CHAT;
+
   const { cwd } = useSettingsStore();
 
-  // Subscribe to Tauri events
+  // Subscribe to Tauri events; every session's events are kept, not just
+  // the one shown
   useEffect(() => {
     const unlisteners: Array<() => void> = [];
 
-    tauri.onSdkMessage((msg) => {
-      handleSdkMessage(msg);
+    tauri.onSdkMessage((msg, sessionId) => {
+      handleSdkMessage(sessionId, msg);
     }).then((fn) => unlisteners.push(fn));
 
-    tauri.onStreamingText((text) => {
-      handleStreamingText(text);
+    tauri.onStreamingText((text, sessionId) => {
+      handleStreamingText(sessionId, text);
     }).then((fn) => unlisteners.push(fn));
 
-    tauri.onTurnComplete((msgs) => {
-      handleTurnComplete(msgs);
+    tauri.onTurnComplete((msgs, sessionId) => {
+      handleTurnComplete(sessionId, msgs);
     }).then((fn) => unlisteners.push(fn));
 
-    tauri.onPermissionRequest((req) => {
-      addPermissionRequest({
+    tauri.onPermissionRequest((req, sessionId) => {
+      addPermissionRequest(sessionId, {
         requestId: req.requestId,
         toolName: req.toolName,
         input: req.input as Record<string, unknown>,
       });
     }).then((fn) => unlisteners.push(fn));
 
-    tauri.onSidecarError((msg) => {
-      console.error("Sidecar error:", msg);
+    tauri.onPermissionCancelled(({ requestId }, sessionId) => {
+      removePermissionRequest(sessionId, requestId);
+    }).then((fn) => unlisteners.push(fn));
+
+    // Answered by voice; without a decision the request is still pending
+    tauri.onPermissionVoiceAnswer(({ requestId, decision }, sessionId) => {
+      if (decision) {
+        removePermissionRequest(sessionId, requestId);
+      }
+    }).then((fn) => unlisteners.push(fn));
+
+    tauri.onSidecarError((msg, sessionId) => {
+      console.error(`Sidecar error in session ${sessionId}:`, msg);
     }).then((fn) => unlisteners.push(fn));
 
     return () => {
       unlisteners.forEach((fn) => fn());
     };
-  }, [handleSdkMessage, handleStreamingText, handleTurnComplete, addPermissionRequest]);
+  }, [handleSdkMessage, handleStreamingText, handleTurnComplete, addPermissionRequest, removePermissionRequest]);
 
   const send = useCallback(
     async (text: string) => {
-      addUserMessage(text);
-      await tauri.sendMessage(text, cwd || undefined);
+      addUserMessage(sessionId, text);
+      try {
+        await tauri.sendMessage(sessionId, text, cwd || undefined);
+      } catch (err) {
+        // Rejected by the sidecar, so no turn is coming
+        setLoading(sessionId, false);
+        throw err;
+      }
     },
-    [addUserMessage, cwd]
+    [addUserMessage, setLoading, sessionId, cwd]
   );
 
   const interrupt = useCallback(async () => {
-    await tauri.interruptSession();
-  }, []);
+    await tauri.interruptSession(sessionId);
+  }, [sessionId]);
 
   const approvePermission = useCallback(
     async (requestId: string) => {
-      await tauri.respondPermission(requestId, "allow");
-      removePermissionRequest(requestId);
+      await tauri.respondPermission(sessionId, requestId, "allow");
+      removePermissionRequest(sessionId, requestId);
     },
-    [removePermissionRequest]
+    [removePermissionRequest, sessionId]
   );
 
   const denyPermission = useCallback(
     async (requestId: string) => {
-      await tauri.respondPermission(requestId, "deny");
-      removePermissionRequest(requestId);
+      await tauri.respondPermission(sessionId, requestId, "deny");
+      removePermissionRequest(sessionId, requestId);
     },
-    [removePermissionRequest]
+    [removePermissionRequest, sessionId]
   );
 
+  const clear = useCallback(() => {
+    clearMessages(sessionId);
+  }, [clearMessages, sessionId]);
+
   return {
+    sessionId,
     messages,
     isLoading,
     streamingContent,
@@ -90,6 +117,6 @@ export function useChat() {
     interrupt,
     approvePermission,
     denyPermission,
-    clearMessages,
+    clearMessages: clear,
   };
 }
@@ -3,84 +3,479 @@ import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
 // Typed Tauri invoke wrappers
 export async function sendMessage(
+  sessionId: string,
   text: string,
   cwd?: string
 ): Promise<void> {
-  return invoke("send_message", { text, cwd });
+  return invoke("send_message", { sessionId, text, cwd });
 }
 
-export async function interruptSession(): Promise<void> {
-  return invoke("interrupt");
+export async function interruptSession(sessionId: string): Promise<void> {
+  return invoke("interrupt", { sessionId });
 }
 
 export async function respondPermission(
+  sessionId: string,
   requestId: string,
   decision: string
 ): Promise<void> {
-  return invoke("respond_permission", { requestId, decision });
+  return invoke("respond_permission", { sessionId, requestId, decision });
 }
 
-export async function setPermissionMode(mode: string): Promise<void> {
-  return invoke("set_permission_mode", { mode });
+export async function setPermissionMode(
+  sessionId: string,
+  mode: string
+): Promise<void> {
+  return invoke("set_permission_mode", { sessionId, mode });
+}
+
+export async function isSidecarRunning(sessionId: string): Promise<boolean> {
+  return invoke("is_sidecar_running", { sessionId });
+}
+
+export interface SessionInfo {
+  sessionId: string;
+  cwd: string | null;
+  permissionMode: string;
+  profile: string | null;
+  running: boolean;
+  generating: boolean;
+  active: boolean;
+}
+
+export async function openSession(
+  sessionId: string,
+  cwd?: string,
+  profile?: string
+): Promise<SessionInfo> {
+  return invoke("open_session", { sessionId, cwd, profile });
+}
+
+export async function closeSession(sessionId: string): Promise<void> {
+  return invoke("close_session", { sessionId });
+}
+
+export async function setActiveSession(sessionId: string): Promise<void> {
+  return invoke("set_active_session", { sessionId });
+}
+
+export async function getSession(sessionId: string): Promise<SessionInfo> {
+  return invoke("get_session", { sessionId });
+}
+
+export async function listSessions(): Promise<SessionInfo[]> {
+  return invoke("list_sessions");
+}
+
+export async function readSidecarLog(
+  sessionId: string,
+  lines: number
+): Promise<string[]> {
+  return invoke("read_sidecar_log", { sessionId, lines });
+}
+
+/** A named set of sidecar environment variables; secret values stay in the backend. */
+export interface EnvProfile {
+  name: string;
+  env: Record<string, string>;
+  secrets: string[];
+}
+
+export async function listEnvProfiles(): Promise<EnvProfile[]> {
+  return invoke("list_env_profiles");
+}
+
+export async function getDefaultEnvProfile(): Promise<string | null> {
+  return invoke("get_default_env_profile");
+}
+
+/** A secret given as `null` keeps its stored value. */
+export async function saveEnvProfile(
+  name: string,
+  env: Record<string, string>,
+  secrets: Record<string, string | null>
+): Promise<void> {
+  return invoke("save_env_profile", { name, env, secrets });
+}
+
+export async function deleteEnvProfile(name: string): Promise<void> {
+  return invoke("delete_env_profile", { name });
+}
+
+export async function setDefaultEnvProfile(name: string | null): Promise<void> {
+  return invoke("set_default_env_profile", { name });
+}
+
+/** Restarts the session's sidecar with the new environment. */
+export async function setSessionProfile(
+  sessionId: string,
+  profile: string | null
+): Promise<void> {
+  return invoke("set_session_profile", { sessionId, profile });
+}
+
+export interface SidecarLaunch {
+  program: string;
+  args: string[];
+  env: Record<string, string>;
+  workingDir: string | null;
+  runtime: { kind: "node" | "bun"; version: string } | null;
+}
+
+/** Why the sidecar cannot be started; `kind` names the check that failed. */
+export interface LaunchFailure {
+  kind:
+    | "notFound"
+    | "missingExecutable"
+    | "notOnPath"
+    | "notExecutable"
+    | "missingScript"
+    | "scriptNotFound"
+    | "missingWorkingDir"
+    | "runtimeUnavailable"
+    | "runtimeTooOld"
+    | "checkFailed";
+  message: string;
+  [detail: string]: unknown;
+}
+
+/** Re-check the sidecar settings; rejects with a `LaunchFailure`. */
+export async function checkSidecarLaunch(): Promise<SidecarLaunch> {
+  return invoke("check_sidecar_launch");
+}
+
+export interface TranscriptSegment {
+  startMs: number;
+  endMs: number;
+  text: string;
+  avgLogprob: number;
+}
+
+export interface FileTranscript {
+  text: string;
+  segments: TranscriptSegment[];
+  durationMs: number;
+}
+
+export async function transcribeFile(path: string): Promise<FileTranscript> {
+  return invoke("transcribe_file", { path });
+}
+
+export interface TranscribeOptions {
+  language: string | null;
+  translate: boolean;
+}
+
+export async function getTranscribeOptions(
+  sessionId: string
+): Promise<TranscribeOptions> {
+  return invoke("get_transcribe_options", { sessionId });
+}
+
+export async function setTranscribeOptions(
+  sessionId: string,
+  options: TranscribeOptions
+): Promise<void> {
+  return invoke("set_transcribe_options", { sessionId, options });
+}
+
+export async function confirmVoiceCommand(
+  id: string,
+  confirmed: boolean
+): Promise<void> {
+  return invoke("confirm_voice_command", { id, confirmed });
+}
+
+export async function setDictationMode(mode: "prose" | "code"): Promise<void> {
+  return invoke("set_dictation_mode", { mode });
+}
+
+export interface Correction {
+  from: string;
+  to: string;
+  count: number;
+}
+
+export async function getCorrections(): Promise<Correction[]> {
+  return invoke("get_corrections");
+}
+
+export async function removeCorrection(from: string): Promise<void> {
+  return invoke("remove_correction", { from });
+}
+
+export interface LongFormSegment {
+  startMs: number;
+  endMs: number;
+  text: string;
+}
+
+export async function startLongForm(cwd?: string): Promise<string> {
+  return invoke("start_long_form", { cwd });
+}
+
+export async function stopLongForm(): Promise<string | null> {
+  return invoke("stop_long_form");
+}
+
+export async function getLongFormPath(): Promise<string | null> {
+  return invoke("long_form_path");
+}
+
+export async function readLongFormTranscript(
+  path: string
+): Promise<LongFormSegment[]> {
+  return invoke("read_long_form_transcript", { path });
+}
+
+export async function sendLongFormRange(
+  sessionId: string,
+  path: string,
+  startMs: number,
+  endMs: number,
+  cwd?: string
+): Promise<void> {
+  return invoke("send_long_form_range", { sessionId, path, startMs, endMs, cwd });
+}
+
+export async function speakText(text: string): Promise<void> {
+  return invoke("speak_text", { text });
+}
+
+export async function stopSpeaking(): Promise<void> {
+  return invoke("stop_speaking");
+}
+
+export async function skipSpeaking(): Promise<void> {
+  return invoke("skip_speaking");
+}
+
+export async function isSpeaking(): Promise<boolean> {
+  return invoke("is_speaking");
 }
 
-export async function isSidecarRunning(): Promise<boolean> {
-  return invoke("is_sidecar_running");
+// Events from a session's sidecar carry the session they belong to
+interface SessionEvent<T> {
+  sessionId: string;
+  payload: T;
+}
+
+function listenSession<T>(
+  name: string,
+  callback: (payload: T, sessionId: string) => void
+): Promise<UnlistenFn> {
+  return listen(name, (event) => {
+    const { sessionId, payload } = event.payload as SessionEvent<T>;
+    callback(payload, sessionId);
+  });
 }
 
 // Typed event listeners
 export function onSdkMessage(
-  callback: (message: unknown) => void
+  callback: (message: unknown, sessionId: string) => void
 ): Promise<UnlistenFn> {
-  return listen("sdk-message", (event) => callback(event.payload));
+  return listenSession("sdk-message", callback);
 }
 
 export function onPermissionRequest(
-  callback: (request: {
-    requestId: string;
-    toolName: string;
-    input: unknown;
-  }) => void
+  callback: (
+    request: {
+      requestId: string;
+      toolName: string;
+      input: unknown;
+    },
+    sessionId: string
+  ) => void
 ): Promise<UnlistenFn> {
-  return listen("permission-request", (event) =>
-    callback(event.payload as { requestId: string; toolName: string; input: unknown })
-  );
+  return listenSession("permission-request", callback);
 }
 
 export function onStreamingText(
-  callback: (text: string) => void
+  callback: (text: string, sessionId: string) => void
 ): Promise<UnlistenFn> {
-  return listen("streaming-text", (event) =>
-    callback(event.payload as string)
-  );
+  return listenSession("streaming-text", callback);
 }
 
 export function onTurnComplete(
-  callback: (messages: unknown[]) => void
+  callback: (messages: unknown[], sessionId: string) => void
 ): Promise<UnlistenFn> {
-  return listen("turn-complete", (event) =>
-    callback(event.payload as unknown[])
-  );
+  return listenSession("turn-complete", callback);
 }
 
+// `agentSessionId` is the sidecar's own id for its agent session
 export function onSessionReady(
+  callback: (agentSessionId: string, sessionId: string) => void
+): Promise<UnlistenFn> {
+  return listenSession("session-ready", callback);
+}
+
+export function onSidecarError(
+  callback: (message: string, sessionId: string) => void
+): Promise<UnlistenFn> {
+  return listenSession("sidecar-error", callback);
+}
+
+export function onSidecarExited(
   callback: (sessionId: string) => void
 ): Promise<UnlistenFn> {
-  return listen("session-ready", (event) =>
-    callback(event.payload as string)
+  return listenSession<null>("sidecar-exited", (_, sessionId) =>
+    callback(sessionId)
   );
 }
 
-export function onSidecarError(
-  callback: (message: string) => void
+export interface SidecarStatus {
+  state: "running" | "restarting" | "failed" | "stopped";
+  exitCode: number | null;
+  signal: number | null;
+  stderr: string[];
+  restartInMs: number | null;
+  attempt: number;
+}
+
+export function onSidecarStatus(
+  callback: (status: SidecarStatus, sessionId: string) => void
+): Promise<UnlistenFn> {
+  return listenSession("sidecar-status", callback);
+}
+
+export function onSidecarIncompatible(
+  callback: (message: string, sessionId: string) => void
+): Promise<UnlistenFn> {
+  return listenSession("sidecar-incompatible", callback);
+}
+
+export function onSidecarLaunchError(
+  callback: (failure: LaunchFailure) => void
+): Promise<UnlistenFn> {
+  return listen("sidecar-launch-error", (event) =>
+    callback(event.payload as LaunchFailure)
+  );
+}
+
+export function onPermissionCancelled(
+  callback: (
+    cancelled: { requestId: string; reason: string },
+    sessionId: string
+  ) => void
+): Promise<UnlistenFn> {
+  return listenSession("permission-cancelled", callback);
+}
+
+export function onTranscriptionFiltered(
+  callback: (filtered: { text: string; reason: string; [key: string]: unknown }) => void
+): Promise<UnlistenFn> {
+  return listen("transcription-filtered", (event) =>
+    callback(event.payload as { text: string; reason: string; [key: string]: unknown })
+  );
+}
+
+export interface VoiceCommand {
+  phrase: string;
+  action: string;
+  mode?: string;
+}
+
+export function onVoiceCommand(
+  callback: (command: VoiceCommand) => void
+): Promise<UnlistenFn> {
+  return listen("voice-command", (event) =>
+    callback(event.payload as VoiceCommand)
+  );
+}
+
+/** A destructive voice command waiting for "confirm" or "cancel". */
+export type PendingVoiceCommand = VoiceCommand & { id: string };
+
+export function onVoiceCommandConfirm(
+  callback: (pending: PendingVoiceCommand) => void
 ): Promise<UnlistenFn> {
-  return listen("sidecar-error", (event) =>
+  return listen("voice-command-confirm", (event) =>
+    callback(event.payload as PendingVoiceCommand)
+  );
+}
+
+export function onVoiceCommandCancelled(
+  callback: (id: string) => void
+): Promise<UnlistenFn> {
+  return listen("voice-command-cancelled", (event) =>
     callback(event.payload as string)
   );
 }
 
-export function onSidecarExited(
-  callback: () => void
+export function onFileTranscriptionProgress(
+  callback: (progress: {
+    path: string;
+    stage: "decoding" | "transcribing";
+    progress: number;
+    partial: string | null;
+  }) => void
+): Promise<UnlistenFn> {
+  return listen("file-transcription-progress", (event) =>
+    callback(
+      event.payload as {
+        path: string;
+        stage: "decoding" | "transcribing";
+        progress: number;
+        partial: string | null;
+      }
+    )
+  );
+}
+
+export function onTranscriptionOriginal(
+  callback: (original: { text: string; language: string | null }) => void
+): Promise<UnlistenFn> {
+  return listen("transcription-original", (event) =>
+    callback(event.payload as { text: string; language: string | null })
+  );
+}
+
+export function onTranscriptionRefined(
+  callback: (refined: { id: string; draft: string; text: string }) => void
+): Promise<UnlistenFn> {
+  return listen("transcription-refined", (event) =>
+    callback(event.payload as { id: string; draft: string; text: string })
+  );
+}
+
+export function onLongFormSegment(
+  callback: (segment: LongFormSegment) => void
+): Promise<UnlistenFn> {
+  return listen("long-form-segment", (event) =>
+    callback(event.payload as LongFormSegment)
+  );
+}
+
+export function onLongFormError(
+  callback: (message: string) => void
+): Promise<UnlistenFn> {
+  return listen("long-form-error", (event) => callback(event.payload as string));
+}
+
+export function onSpeechStarted(
+  callback: (text: string) => void
+): Promise<UnlistenFn> {
+  return listen("speech-started", (event) => callback(event.payload as string));
+}
+
+export function onSpeechFinished(callback: () => void): Promise<UnlistenFn> {
+  return listen("speech-finished", () => callback());
+}
+
+export function onSpeechError(
+  callback: (message: string) => void
+): Promise<UnlistenFn> {
+  return listen("speech-error", (event) => callback(event.payload as string));
+}
+
+export interface VoiceAnswer {
+  requestId: string;
+  decision: "allow" | "deny" | null;
+  transcript: string;
+}
+
+export function onPermissionVoiceAnswer(
+  callback: (answer: VoiceAnswer, sessionId: string) => void
 ): Promise<UnlistenFn> {
-  return listen("sidecar-exited", () => callback());
+  return listenSession("permission-voice-answer", callback);
 }
@@ -1,24 +1,47 @@
 import { create } from "zustand";
 import type { ChatMessage, PermissionRequest, SdkMessage, SdkContent } from "../lib/types";
 
-interface ChatState {
+export interface SessionChat {
   messages: ChatMessage[];
   pendingPermissions: PermissionRequest[];
   isLoading: boolean;
   streamingContent: string;
+}
 
-  addUserMessage: (text: string) => void;
-  handleSdkMessage: (message: unknown) => void;
-  handleStreamingText: (text: string) => void;
-  handleTurnComplete: (messages: unknown[]) => void;
-  addPermissionRequest: (request: PermissionRequest) => void;
-  removePermissionRequest: (requestId: string) => void;
-  setLoading: (loading: boolean) => void;
-  clearMessages: () => void;
+export const EMPTY_CHAT: SessionChat = {
+  messages: [],
+  pendingPermissions: [],
+  isLoading: false,
+  streamingContent: "",
+};
+
+// Every session's conversation is kept, so background sessions keep
+// receiving their events while another one is shown
+interface ChatState {
+  chats: Record<string, SessionChat>;
+
+  addUserMessage: (sessionId: string, text: string) => void;
+  handleSdkMessage: (sessionId: string, message: unknown) => void;
+  handleStreamingText: (sessionId: string, text: string) => void;
+  handleTurnComplete: (sessionId: string, messages: unknown[]) => void;
+  addPermissionRequest: (sessionId: string, request: PermissionRequest) => void;
+  removePermissionRequest: (sessionId: string, requestId: string) => void;
+  setLoading: (sessionId: string, loading: boolean) => void;
+  clearMessages: (sessionId: string) => void;
+  removeChat: (sessionId: string) => void;
 }
 
 let messageCounter = 0;
 
+function updateChat(
+  state: ChatState,
+  sessionId: string,
+  update: (chat: SessionChat) => Partial<SessionChat>
+): Pick<ChatState, "chats"> {
+  const chat = state.chats[sessionId] ?? EMPTY_CHAT;
+  return { chats: { ...state.chats, [sessionId]: { ...chat, ...update(chat) } } };
+}
+
 function extractTextFromContent(content: SdkContent[]): string {
   return content
     .filter((c) => c.type === "text" && c.text)
@@ -38,26 +61,25 @@ function extractToolUseFromContent(content: SdkContent[]) {
 }
 
 export const useChatStore = create<ChatState>((set) => ({
-  messages: [],
-  pendingPermissions: [],
-  isLoading: false,
-  streamingContent: "",
+  chats: {},
 
-  addUserMessage: (text: string) => {
+  addUserMessage: (sessionId: string, text: string) => {
     const msg: ChatMessage = {
       id: `msg_${++messageCounter}`,
       role: "user",
       content: text,
       timestamp: Date.now(),
     };
-    set((state) => ({
-      messages: [...state.messages, msg],
-      isLoading: true,
-      streamingContent: "",
-    }));
+    set((state) =>
+      updateChat(state, sessionId, (chat) => ({
+        messages: [...chat.messages, msg],
+        isLoading: true,
+        streamingContent: "",
+      }))
+    );
   },
 
-  handleSdkMessage: (rawMessage: unknown) => {
+  handleSdkMessage: (sessionId: string, rawMessage: unknown) => {
     const message = rawMessage as SdkMessage;
     if (!message || !message.type) return;
 
@@ -74,10 +96,12 @@ export const useChatStore = create<ChatState>((set) => ({
           timestamp: Date.now(),
           toolUse: toolUse.length > 0 ? toolUse : undefined,
         };
-        set((state) => ({
-          messages: [...state.messages, msg],
-          streamingContent: "",
-        }));
+        set((state) =>
+          updateChat(state, sessionId, (chat) => ({
+            messages: [...chat.messages, msg],
+            streamingContent: "",
+          }))
+        );
       }
     }
 
@@ -85,70 +109,88 @@ export const useChatStore = create<ChatState>((set) => ({
     if (message.type === "tool_result") {
       const content = message.content as string | undefined;
       if (content) {
-        set((state) => {
-          const messages = [...state.messages];
-          // Find the last assistant message with a matching tool use
-          for (let i = messages.length - 1; i >= 0; i--) {
-            if (messages[i].toolUse) {
-              const toolUse = messages[i].toolUse!;
-              const tool = toolUse.find((t) => t.status === "completed" && !t.output);
-              if (tool) {
-                tool.output = content;
-                break;
+        set((state) =>
+          updateChat(state, sessionId, (chat) => {
+            const messages = [...chat.messages];
+            // Find the last assistant message with a matching tool use
+            for (let i = messages.length - 1; i >= 0; i--) {
+              if (messages[i].toolUse) {
+                const toolUse = messages[i].toolUse!;
+                const tool = toolUse.find((t) => t.status === "completed" && !t.output);
+                if (tool) {
+                  tool.output = content;
+                  break;
+                }
               }
             }
-          }
-          return { messages };
-        });
+            return { messages };
+          })
+        );
       }
     }
   },
 
-  handleStreamingText: (text: string) => {
-    set((state) => ({
-      streamingContent: state.streamingContent + text,
-    }));
+  handleStreamingText: (sessionId: string, text: string) => {
+    set((state) =>
+      updateChat(state, sessionId, (chat) => ({
+        streamingContent: chat.streamingContent + text,
+      }))
+    );
   },
 
-  handleTurnComplete: (_messages: unknown[]) => {
-    set((state) => {
-      // If there's remaining streaming content, add it as a message
-      const newMessages = [...state.messages];
-      if (state.streamingContent) {
-        newMessages.push({
-          id: `msg_${++messageCounter}`,
-          role: "assistant",
-          content: state.streamingContent,
-          timestamp: Date.now(),
-        });
-      }
-      return {
-        messages: newMessages,
-        isLoading: false,
-        streamingContent: "",
-      };
-    });
+  handleTurnComplete: (sessionId: string, _messages: unknown[]) => {
+    set((state) =>
+      updateChat(state, sessionId, (chat) => {
+        // If there's remaining streaming content, add it as a message
+        const newMessages = [...chat.messages];
+        if (chat.streamingContent) {
+          newMessages.push({
+            id: `msg_${++messageCounter}`,
+            role: "assistant",
+            content: chat.streamingContent,
+            timestamp: Date.now(),
+          });
+        }
+        return {
+          messages: newMessages,
+          isLoading: false,
+          streamingContent: "",
+        };
+      })
+    );
   },
 
-  addPermissionRequest: (request: PermissionRequest) => {
-    set((state) => ({
-      pendingPermissions: [...state.pendingPermissions, request],
-    }));
+  addPermissionRequest: (sessionId: string, request: PermissionRequest) => {
+    set((state) =>
+      updateChat(state, sessionId, (chat) => ({
+        pendingPermissions: [...chat.pendingPermissions, request],
+      }))
+    );
   },
 
-  removePermissionRequest: (requestId: string) => {
-    set((state) => ({
-      pendingPermissions: state.pendingPermissions.filter(
-        (p) => p.requestId !== requestId
-      ),
-    }));
+  removePermissionRequest: (sessionId: string, requestId: string) => {
+    set((state) =>
+      updateChat(state, sessionId, (chat) => ({
+        pendingPermissions: chat.pendingPermissions.filter(
+          (p) => p.requestId !== requestId
+        ),
+      }))
+    );
   },
 
-  setLoading: (loading: boolean) => {
-    set({ isLoading: loading });
+  setLoading: (sessionId: string, loading: boolean) => {
+    set((state) => updateChat(state, sessionId, () => ({ isLoading: loading })));
   },
 
-  clearMessages: () => {
-    set({ messages: [], streamingContent: "", isLoading: false, pendingPermissions: [] });
+  clearMessages: (sessionId: string) => {
+    set((state) => updateChat(state, sessionId, () => EMPTY_CHAT));
+  },
+
+  removeChat: (sessionId: string) => {
+    set((state) => {
+      const chats = { ...state.chats };
+      delete chats[sessionId];
+      return { chats };
+    });
   },
 }));
@@ -4,6 +4,8 @@ export interface Session {
   id: string;
   name: string;
   cwd: string;
+  /** Environment profile the session's sidecar runs with. */
+  profile: string | null;
   createdAt: number;
   lastActiveAt: number;
   messageCount: number;
@@ -13,26 +15,31 @@ interface SessionState {
   sessions: Session[];
   activeSessionId: string | null;
 
-  createSession: (name: string, cwd: string) => string;
+  createSession: (name: string, cwd: string, profile: string | null) => string;
   setActiveSession: (id: string) => void;
   renameSession: (id: string, name: string) => void;
   removeSession: (id: string) => void;
+  setSessionProfile: (id: string, profile: string | null) => void;
   updateActivity: (id: string) => void;
   incrementMessages: (id: string) => void;
 }
 
 let sessionCounter = 0;
 
+// The backend's session, used while no other session is selected
+export const DEFAULT_SESSION_ID = "default";
+
 export const useSessionStore = create<SessionState>((set) => ({
   sessions: [],
   activeSessionId: null,
 
-  createSession: (name: string, cwd: string) => {
+  createSession: (name: string, cwd: string, profile: string | null) => {
     const id = `session_${++sessionCounter}_${Date.now()}`;
     const session: Session = {
       id,
       name,
       cwd,
+      profile,
       createdAt: Date.now(),
       lastActiveAt: Date.now(),
       messageCount: 0,
@@ -64,6 +71,14 @@ export const useSessionStore = create<SessionState>((set) => ({
     }));
   },
 
+  setSessionProfile: (id: string, profile: string | null) => {
+    set((state) => ({
+      sessions: state.sessions.map((s) =>
+        s.id === id ? { ...s, profile } : s
+      ),
+    }));
+  },
+
   updateActivity: (id: string) => {
     set((state) => ({
       sessions: state.sessions.map((s) =>